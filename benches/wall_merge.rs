@@ -0,0 +1,77 @@
+//! Compares the wall merging strategies on large generated grids.
+//!
+//! Run with `cargo bench --bench wall_merge`.
+// Its unit tests come along too, but they're run with the game's own tests
+#[path = "../src/wall_merge.rs"]
+#[allow(dead_code)]
+mod wall_merge;
+
+use bevy_ecs_ldtk::prelude::*;
+use std::{collections::HashSet, time::Instant};
+use wall_merge::{GridRect, WallMerge};
+
+const SIZE: i32 = 512;
+
+const ITERATIONS: u32 = 10;
+
+/// Cells of a `SIZE` by `SIZE` grid that `is_wall` picks, visiting them row by row.
+fn grid(mut is_wall: impl FnMut(i32, i32) -> bool) -> HashSet<GridCoords> {
+    (0..SIZE)
+        .flat_map(|y| (0..SIZE).map(move |x| GridCoords { x, y }))
+        .filter(|coords| is_wall(coords.x, coords.y))
+        .collect()
+}
+
+/// Noise, like a cave that's been carved at random, and the same every run.
+fn cave() -> HashSet<GridCoords> {
+    let mut seed: u32 = 51;
+
+    grid(|_, _| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        (seed >> 16) % 100 < 55
+    })
+}
+
+/// Ledges that step up and down, so their rows rarely line up.
+fn staircase() -> HashSet<GridCoords> {
+    grid(|x, y| (y + x / 3) % 16 < 10)
+}
+
+/// Solid ground with a border and regular floating platforms.
+fn rooms() -> HashSet<GridCoords> {
+    grid(|x, y| {
+        y < 8 || !(4..SIZE - 4).contains(&x) || y >= SIZE - 4 || (y % 24 == 0 && x % 32 < 20)
+    })
+}
+
+fn area(rects: &[GridRect]) -> i32 {
+    rects
+        .iter()
+        .map(|rect| (rect.right - rect.left + 1) * (rect.top - rect.bottom + 1))
+        .sum()
+}
+
+fn main() {
+    for (name, cells) in [
+        ("cave", cave()),
+        ("staircase", staircase()),
+        ("rooms", rooms()),
+    ] {
+        for wall_merge in [WallMerge::Plates, WallMerge::Greedy] {
+            let start = Instant::now();
+            let mut rects = Vec::new();
+
+            for _ in 0..ITERATIONS {
+                rects = wall_merge.merge(SIZE, SIZE, &cells);
+            }
+
+            let elapsed = start.elapsed() / ITERATIONS;
+            assert_eq!(area(&rects), cells.len() as i32);
+
+            println!(
+                "{name:>9} {wall_merge:?}: {} rectangles in {elapsed:?}",
+                rects.len()
+            );
+        }
+    }
+}
@@ -0,0 +1,409 @@
+//! A kinematic character controller built on rapier scene queries.
+//!
+//! Entities with a [CharacterController] should have a [RigidBody::KinematicPositionBased] and a
+//! cuboid [Collider].
+//! Instead of letting rapier solve their contacts, their [CharacterController::velocity] is
+//! integrated here by shape-casting through the world and sliding along whatever gets in the way.
+//!
+//! [OneWay] walls are only collided with while the character is above them.
+use crate::{
+    fixed_timestep::{PhysicsAppExt, PHYSICS_DELTA},
+    player::GroundDetection,
+    wall::OneWay,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_rapier2d::{
+    parry::{
+        self,
+        math::{Isometry, Vector},
+    },
+    prelude::*,
+};
+use iyes_loopless::prelude::*;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub struct CharacterControllerPlugin;
+
+impl Plugin for CharacterControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_physics_system(
+            move_characters
+                .run_in_state(GameState::Gameplay)
+                .label("character_controller")
+                .after("movement"),
+        );
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Component)]
+pub struct CharacterController {
+    /// Velocity the character wants to move at, in pixels per second.
+    ///
+    /// Components of it that run into something get removed when the character moves.
+    pub velocity: Vec2,
+    /// Tallest ledge the character can walk up without jumping.
+    pub max_step_height: f32,
+    /// How far down the character will be pulled to stay on the ground when walking off a slope
+    /// or step.
+    pub snap_distance: f32,
+    /// Gap kept between the character and anything it collides with.
+    pub skin: f32,
+    /// Whether the character is being squeezed between solids it can't escape from.
+    pub crushed: bool,
+    /// Seconds left to fall through [OneWay] walls instead of landing on them.
+    pub drop_through: f32,
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        CharacterController {
+            velocity: Vec2::ZERO,
+            max_step_height: 8.,
+            snap_distance: 4.,
+            skin: 0.1,
+            crushed: false,
+            drop_through: 0.,
+        }
+    }
+}
+
+/// Steepest surface, given by the y value of its normal, that can be walked on.
+const MIN_GROUND_NORMAL_Y: f32 = 0.6;
+
+const MAX_SLIDES: usize = 4;
+
+const MAX_DEPENETRATIONS: usize = 4;
+
+/// Slowest speed to hit a surface at that will bounce off of it, if it has any [Restitution].
+///
+/// Slower hits stop dead, so characters can come to rest on bouncy surfaces.
+const MIN_BOUNCE_SPEED: f32 = 100.;
+
+type Solids<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static GlobalTransform,
+        &'static Collider,
+        Option<&'static OneWay>,
+        Option<&'static Restitution>,
+    ),
+    Without<CharacterController>,
+>;
+
+type Characters<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Transform,
+        &'static mut GlobalTransform,
+        &'static Collider,
+        &'static mut CharacterController,
+        Option<&'static GroundDetection>,
+    ),
+>;
+
+/// Scene queries used to move a single character through the world.
+struct CharacterMover<'a, 'w, 's> {
+    rapier_context: &'a RapierContext,
+    solids: &'a Solids<'w, 's>,
+    entity: Entity,
+    shape: Collider,
+    half_extents: Vec2,
+    skin: f32,
+    dropping: bool,
+}
+
+/// Result of moving a character as far as possible in a straight line.
+struct Sweep {
+    moved: Vec2,
+    /// Normal of the surface that was hit, pointing away from it.
+    hit_normal: Option<Vec2>,
+    hit_entity: Option<Entity>,
+}
+
+impl<'a, 'w, 's> CharacterMover<'a, 'w, 's> {
+    fn filter(&self) -> QueryFilter<'_> {
+        QueryFilter::new()
+            .exclude_sensors()
+            .exclude_rigid_body(self.entity)
+    }
+
+    /// Whether `other` gets in the way of the character when it's at `position`.
+    ///
+    /// [OneWay] walls only do when the character's feet are on or above them.
+    fn blocks(&self, other: Entity, position: Vec2) -> bool {
+        match self.solids.get(other) {
+            Ok((other_transform, other_collider, Some(_), _)) => {
+                !self.dropping
+                    && OneWay::supports(
+                        other_transform,
+                        other_collider,
+                        position.y - self.half_extents.y,
+                    )
+            }
+            _ => true,
+        }
+    }
+
+    /// How much of the speed the character hits `other` at is bounced back.
+    fn restitution(&self, other: Entity) -> f32 {
+        match self.solids.get(other) {
+            Ok((_, _, _, Some(restitution))) => restitution.coefficient,
+            _ => 0.,
+        }
+    }
+
+    /// Moves from `position` by `motion` until something is hit.
+    fn sweep(&self, position: Vec2, motion: Vec2) -> Sweep {
+        self.sweep_excluding(position, motion, None)
+    }
+
+    /// Like [CharacterMover::sweep], but passes straight through the `excluded` collider.
+    fn sweep_excluding(&self, position: Vec2, motion: Vec2, excluded: Option<Entity>) -> Sweep {
+        let distance = motion.length();
+
+        if distance <= f32::EPSILON {
+            return Sweep {
+                moved: Vec2::ZERO,
+                hit_normal: None,
+                hit_entity: None,
+            };
+        }
+
+        let direction = motion / distance;
+        let blocks = |other| self.blocks(other, position);
+        let mut filter = self.filter().predicate(&blocks);
+        if let Some(excluded) = excluded {
+            filter = filter.exclude_collider(excluded);
+        }
+
+        match self.rapier_context.cast_shape(
+            position,
+            0.,
+            direction,
+            &self.shape,
+            distance + self.skin,
+            filter,
+        ) {
+            Some((hit_entity, toi)) => {
+                // Already overlapping, so push straight back
+                let hit_normal = if toi.toi <= 0. {
+                    -direction
+                } else {
+                    -toi.normal1
+                };
+
+                Sweep {
+                    moved: direction * (toi.toi - self.skin).clamp(0., distance),
+                    hit_normal: Some(hit_normal),
+                    hit_entity: Some(hit_entity),
+                }
+            }
+            None => Sweep {
+                moved: motion,
+                hit_normal: None,
+                hit_entity: None,
+            },
+        }
+    }
+
+    /// Pushes the character out of anything that has moved into it, along the contact normal.
+    ///
+    /// Shapes that parry can't compute contacts with, like compounds, can't be pushed out of.
+    /// Returns the new position, and whether the character is still stuck.
+    fn depenetrate(&self, mut position: Vec2) -> (Vec2, bool) {
+        let inner_shape = Collider::cuboid(
+            self.half_extents.x - self.skin,
+            self.half_extents.y - self.skin,
+        );
+
+        for _ in 0..MAX_DEPENETRATIONS {
+            let mut push: Option<Vec2> = None;
+            let blocks = |other| self.blocks(other, position);
+
+            self.rapier_context.intersections_with_shape(
+                position,
+                0.,
+                &inner_shape,
+                self.filter().predicate(&blocks),
+                |other| {
+                    if let Ok((other_transform, other_collider, _, _)) = self.solids.get(other) {
+                        // Colliders are already scaled by their transform, so only the
+                        // translation and rotation are needed
+                        let (_, rotation, translation) =
+                            other_transform.to_scale_rotation_translation();
+                        let (angle, _, _) = rotation.to_euler(EulerRot::ZYX);
+                        let other_position =
+                            Isometry::new(Vector::new(translation.x, translation.y), angle);
+
+                        if let Ok(Some(contact)) = parry::query::contact(
+                            &Isometry::translation(position.x, position.y),
+                            &*inner_shape.raw,
+                            &other_position,
+                            &*other_collider.raw,
+                            0.,
+                        ) {
+                            // The contact distance is negative while they overlap
+                            push = Some(
+                                Vec2::new(contact.normal2.x, contact.normal2.y) * -contact.dist,
+                            );
+
+                            return false;
+                        }
+                    }
+
+                    true
+                },
+            );
+
+            match push {
+                Some(push) => position += push + push.normalize_or_zero() * self.skin,
+                None => return (position, false),
+            }
+        }
+
+        let blocks = |other| self.blocks(other, position);
+        let stuck = self
+            .rapier_context
+            .intersection_with_shape(position, 0., &inner_shape, self.filter().predicate(&blocks))
+            .is_some();
+
+        (position, stuck)
+    }
+}
+
+fn is_ground(normal: Vec2) -> bool {
+    normal.y >= MIN_GROUND_NORMAL_Y
+}
+
+pub fn move_characters(
+    rapier_context: Res<RapierContext>,
+    mut characters: Characters,
+    solids: Solids,
+) {
+    for (entity, mut transform, mut global_transform, collider, mut controller, ground_detection) in
+        characters.iter_mut()
+    {
+        let (scale, _, translation) = global_transform.to_scale_rotation_translation();
+        let half_extents = collider
+            .as_cuboid()
+            .map(|cuboid| cuboid.half_extents())
+            .unwrap_or_default()
+            * scale.truncate();
+
+        controller.drop_through = (controller.drop_through - PHYSICS_DELTA).max(0.);
+
+        let mover = CharacterMover {
+            rapier_context: &rapier_context,
+            solids: &solids,
+            entity,
+            shape: Collider::cuboid(half_extents.x, half_extents.y),
+            half_extents,
+            skin: controller.skin,
+            dropping: controller.drop_through > 0.,
+        };
+
+        // Things like platforms may have moved into the character since last frame
+        let (start, crushed) = mover.depenetrate(translation.truncate());
+        controller.crushed = crushed;
+
+        let was_on_ground = ground_detection.map(|g| g.on_ground).unwrap_or(false);
+
+        let mut position = start;
+        let mut velocity = controller.velocity;
+        let mut remaining = velocity * PHYSICS_DELTA;
+
+        for _ in 0..MAX_SLIDES {
+            let sweep = mover.sweep(position, remaining);
+            position += sweep.moved;
+            remaining -= sweep.moved;
+
+            let normal = match sweep.hit_normal {
+                Some(normal) => normal,
+                None => break,
+            };
+
+            // Try walking up short ledges instead of stopping at them
+            if was_on_ground && !is_ground(normal) && normal.y.abs() < 0.3 && remaining.x != 0. {
+                let up = mover.sweep(position, Vec2::Y * controller.max_step_height);
+                let across = mover.sweep(position + up.moved, Vec2::new(remaining.x, 0.));
+                let down = mover.sweep(position + up.moved + across.moved, -up.moved);
+
+                if across.moved.x.abs() > f32::EPSILON
+                    && down.hit_normal.map(is_ground).unwrap_or(false)
+                {
+                    position += up.moved + across.moved + down.moved;
+                    remaining.x -= across.moved.x;
+                    continue;
+                }
+            }
+
+            // Slide along the surface, dropping any motion into it
+            let into_surface = remaining.dot(normal);
+            if into_surface < 0. {
+                remaining -= normal * into_surface;
+            }
+
+            let velocity_into_surface = velocity.dot(normal);
+            if velocity_into_surface < 0. {
+                // Bouncy surfaces send some of it back the other way
+                let bounce = match sweep.hit_entity {
+                    Some(hit_entity) if -velocity_into_surface >= MIN_BOUNCE_SPEED => {
+                        mover.restitution(hit_entity)
+                    }
+                    _ => 0.,
+                };
+
+                velocity -= normal * velocity_into_surface * (1. + bounce);
+            }
+        }
+
+        // Stick to the ground when walking down slopes and steps, rather than launching off them.
+        // This also closes any gap that was already left between the feet and the ground.
+        if was_on_ground && velocity.y <= 0. {
+            let gap = ground_detection.map(|g| g.distance).unwrap_or(0.);
+            let down = mover.sweep(position, Vec2::NEG_Y * (controller.snap_distance + gap));
+
+            if down.hit_normal.map(is_ground).unwrap_or(false) {
+                position += down.moved;
+            }
+        }
+
+        // Being carried by the ground isn't the character's own velocity,
+        // so it's moved separately and isn't lost when the character bumps into things.
+        // The ground is still where it was before this step, so it's passed through;
+        // otherwise ground moving down would leave a gap under the character every step.
+        if let Some(ground_detection) = ground_detection {
+            let mut carried = ground_detection.ground_velocity * PHYSICS_DELTA;
+
+            for _ in 0..MAX_SLIDES {
+                let sweep =
+                    mover.sweep_excluding(position, carried, ground_detection.ground_entity);
+                position += sweep.moved;
+                carried -= sweep.moved;
+
+                match sweep.hit_normal {
+                    Some(normal) if carried.dot(normal) < 0. => {
+                        carried -= normal * carried.dot(normal);
+                    }
+                    Some(_) => (),
+                    None => break,
+                }
+            }
+        }
+
+        controller.velocity = velocity;
+
+        // The transform is relative to the level, so only apply the difference.
+        // The global transform is kept up to date for later steps in the same frame.
+        let offset = position - translation.truncate();
+        if offset != Vec2::ZERO {
+            transform.translation += offset.extend(0.);
+            *global_transform =
+                GlobalTransform::from_translation(offset.extend(0.)) * *global_transform;
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{
+    character_controller::CharacterController,
+    dash::Dash,
+    fixed_timestep::InterpolatedTransform,
+    history::{RewindTo, TimeScale, TimeSinceLevelStart},
+    level_transition::LevelStart,
+    player::{player_touched, ColliderBundle, GroundDetection, Player, Vitality},
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+
+pub struct CheckpointPlugin;
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReachedCheckpoint>()
+            .add_system(reach_checkpoint.run_in_state(GameState::Gameplay))
+            .add_system(forget_checkpoint.run_on_event::<LevelStart>())
+            .add_system(
+                return_to_checkpoint
+                    .run_in_state(GameState::Gameplay)
+                    .run_on_event::<RewindTo>(),
+            )
+            .add_system(highlight_reached_checkpoint.run_in_state(GameState::Gameplay))
+            .register_ldtk_entity::<CheckpointBundle>("Checkpoint");
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct Checkpoint;
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct CheckpointBundle {
+    #[sprite_sheet_bundle]
+    #[bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    pub checkpoint: Checkpoint,
+    #[from_entity_instance]
+    #[bundle]
+    pub collider_bundle: ColliderBundle,
+    pub sensor: Sensor,
+}
+
+/// Where and when the player last touched a [Checkpoint] in the current level.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct CheckpointState {
+    pub entity: Entity,
+    pub translation: Vec3,
+    pub timestamp: f32,
+}
+
+/// The checkpoint that restarting the level will go back to, if any.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct ReachedCheckpoint(pub Option<CheckpointState>);
+
+fn reach_checkpoint(
+    player_query: Query<&Vitality, With<Player>>,
+    player_transforms: Query<&Transform, With<Player>>,
+    checkpoint_query: Query<Entity, With<Checkpoint>>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut reached_checkpoint: ResMut<ReachedCheckpoint>,
+    time_scale: Res<TimeScale>,
+    time_since_level_start: Res<TimeSinceLevelStart>,
+) {
+    for collision in collision_events.iter() {
+        let touched = player_touched(collision, &player_query, &time_scale)
+            .filter(|entity| checkpoint_query.contains(*entity));
+
+        if let (Some(checkpoint_entity), Ok(transform)) = (touched, player_transforms.get_single())
+        {
+            // Touching the same checkpoint again shouldn't lose the player any time
+            if reached_checkpoint.0.map(|c| c.entity) != Some(checkpoint_entity) {
+                reached_checkpoint.0 = Some(CheckpointState {
+                    entity: checkpoint_entity,
+                    translation: transform.translation,
+                    timestamp: time_since_level_start.0,
+                });
+            }
+        }
+    }
+}
+
+fn forget_checkpoint(mut reached_checkpoint: ResMut<ReachedCheckpoint>) {
+    *reached_checkpoint = ReachedCheckpoint::default();
+}
+
+type ReturningPlayers<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Transform,
+        Option<&'static mut InterpolatedTransform>,
+        &'static mut CharacterController,
+        &'static mut GroundDetection,
+        &'static mut Dash,
+    ),
+    With<Player>,
+>;
+
+/// Puts the player back at the checkpoint when the timeline jumps back to it.
+fn return_to_checkpoint(
+    reached_checkpoint: Res<ReachedCheckpoint>,
+    mut player_query: ReturningPlayers,
+) {
+    if let Some(checkpoint) = reached_checkpoint.0 {
+        for (mut transform, interpolated, mut controller, mut ground_detection, mut dash) in
+            player_query.iter_mut()
+        {
+            transform.translation = checkpoint.translation;
+
+            if let Some(mut interpolated) = interpolated {
+                interpolated.teleport(checkpoint.translation);
+            }
+
+            controller.velocity = Vec2::ZERO;
+            ground_detection.ground_velocity = Vec2::ZERO;
+            *dash = Dash::default();
+        }
+    }
+}
+
+fn highlight_reached_checkpoint(
+    reached_checkpoint: Res<ReachedCheckpoint>,
+    mut checkpoint_query: Query<(Entity, &mut TextureAtlasSprite), With<Checkpoint>>,
+) {
+    if reached_checkpoint.is_changed() {
+        let reached_entity = reached_checkpoint.0.map(|c| c.entity);
+
+        for (entity, mut sprite) in checkpoint_query.iter_mut() {
+            sprite.color = if Some(entity) == reached_entity {
+                Color::rgb(1., 0.9, 0.4)
+            } else {
+                Color::WHITE
+            };
+        }
+    }
+}
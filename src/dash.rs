@@ -0,0 +1,207 @@
+use crate::{
+    character_controller::CharacterController,
+    fixed_timestep::{PhysicsAppExt, PHYSICS_DELTA},
+    level_transition::LevelStart,
+    player::{GroundDetection, Player, PlayerAnimationState, PlayerInput, Vitality},
+    AssetHolder, GameState,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use iyes_loopless::prelude::*;
+
+pub struct DashPlugin;
+
+impl Plugin for DashPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DashUnlocked(false))
+            .add_system(unlock_dash_for_level.run_on_event::<LevelStart>())
+            .add_physics_system(
+                dash.run_in_state(GameState::Gameplay)
+                    .after("movement")
+                    .before("character_controller"),
+            )
+            .add_system(spawn_afterimages.run_in_state(GameState::Gameplay))
+            .add_system(fade_afterimages.run_in_state(GameState::Gameplay));
+    }
+}
+
+const DASH_SPEED: f32 = 600.;
+
+const DASH_DURATION: f32 = 0.15;
+
+const DASH_COOLDOWN: f32 = 0.6;
+
+/// Horizontal speed the player is left with when a dash ends.
+const DASH_EXIT_SPEED: f32 = 250.;
+
+const AFTERIMAGE_INTERVAL: f32 = 0.03;
+
+const AFTERIMAGE_LIFETIME: f32 = 0.3;
+
+/// Whether the current level allows dashing.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct DashUnlocked(pub bool);
+
+#[derive(Copy, Clone, PartialEq, Debug, Default, Component)]
+pub struct Dash {
+    /// Seconds left in the current dash.
+    pub remaining: f32,
+    /// Seconds until the player can dash again.
+    pub cooldown: f32,
+    /// Direction of the current dash, -1 for left and 1 for right.
+    pub direction: f32,
+    /// Whether the one dash allowed per jump has been used.
+    pub air_dash_used: bool,
+}
+
+impl Dash {
+    pub fn is_dashing(&self) -> bool {
+        self.remaining > 0.
+    }
+}
+
+fn unlock_dash_for_level(
+    mut dash_unlocked: ResMut<DashUnlocked>,
+    asset_holder: Res<AssetHolder>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    level_selection: Res<LevelSelection>,
+) {
+    let unlocked = ldtk_assets
+        .get(&asset_holder.ldtk)
+        .and_then(|ldtk_asset| ldtk_asset.get_level(&level_selection))
+        .and_then(|level| {
+            level
+                .field_instances
+                .iter()
+                .find(|f| f.identifier == "Dash")
+        })
+        .map(|field| matches!(field.value, FieldValue::Bool(true)))
+        .unwrap_or(false);
+
+    *dash_unlocked = DashUnlocked(unlocked);
+}
+
+type DashingPlayers<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Dash,
+        &'static mut CharacterController,
+        &'static mut PlayerAnimationState,
+        &'static mut TextureAtlasSprite,
+        &'static GroundDetection,
+        &'static Vitality,
+    ),
+    With<Player>,
+>;
+
+fn dash(
+    mut input: ResMut<PlayerInput>,
+    dash_unlocked: Res<DashUnlocked>,
+    mut players: DashingPlayers,
+) {
+    let dash_pressed = std::mem::take(&mut input.dash_pressed);
+
+    for (mut dash, mut controller, mut animation_state, mut sprite, ground_detection, vitality) in
+        players.iter_mut()
+    {
+        dash.cooldown = (dash.cooldown - PHYSICS_DELTA).max(0.);
+
+        if ground_detection.on_ground && !dash.is_dashing() {
+            dash.air_dash_used = false;
+        }
+
+        if dash_pressed
+            && dash_unlocked.0
+            && *vitality == Vitality::Alive
+            && dash.cooldown <= 0.
+            && !dash.air_dash_used
+        {
+            dash.direction = match (input.left, input.right) {
+                (true, false) => -1.,
+                (false, true) => 1.,
+                _ if sprite.flip_x => -1.,
+                _ => 1.,
+            };
+            dash.remaining = DASH_DURATION;
+            dash.cooldown = DASH_COOLDOWN;
+            dash.air_dash_used = !ground_detection.on_ground;
+        }
+
+        if dash.is_dashing() {
+            dash.remaining -= PHYSICS_DELTA;
+
+            // Dashes go straight, ignoring gravity
+            controller.velocity = Vec2::new(dash.direction * DASH_SPEED, 0.);
+
+            if !dash.is_dashing() {
+                controller.velocity.x = dash.direction * DASH_EXIT_SPEED;
+            }
+
+            sprite.flip_x = dash.direction < 0.;
+
+            if *animation_state != PlayerAnimationState::Dashing {
+                *animation_state = PlayerAnimationState::Dashing;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Component)]
+struct Afterimage(Timer);
+
+fn spawn_afterimages(
+    mut commands: Commands,
+    players: Query<
+        (
+            &Dash,
+            &GlobalTransform,
+            &TextureAtlasSprite,
+            &Handle<TextureAtlas>,
+        ),
+        With<Player>,
+    >,
+    time: Res<Time>,
+    mut since_last_afterimage: Local<f32>,
+) {
+    *since_last_afterimage += time.delta_seconds();
+
+    for (dash, global_transform, sprite, texture_atlas) in players.iter() {
+        if dash.is_dashing() && *since_last_afterimage >= AFTERIMAGE_INTERVAL {
+            *since_last_afterimage = 0.;
+
+            commands
+                .spawn_bundle(SpriteSheetBundle {
+                    sprite: TextureAtlasSprite {
+                        index: sprite.index,
+                        flip_x: sprite.flip_x,
+                        color: Color::rgba(1., 1., 1., 0.5),
+                        ..default()
+                    },
+                    texture_atlas: texture_atlas.clone(),
+                    // just behind the player
+                    transform: Transform::from_translation(
+                        global_transform.translation() - Vec3::Z * 0.1,
+                    ),
+                    ..default()
+                })
+                .insert(Afterimage(Timer::from_seconds(AFTERIMAGE_LIFETIME, false)));
+        }
+    }
+}
+
+fn fade_afterimages(
+    mut commands: Commands,
+    mut afterimages: Query<(Entity, &mut Afterimage, &mut TextureAtlasSprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut afterimage, mut sprite) in afterimages.iter_mut() {
+        afterimage.0.tick(time.delta());
+
+        if afterimage.0.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            sprite.color.set_a(0.5 * afterimage.0.percent_left());
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Runs physics in a fixed-timestep stage, so that movement is the same at any frame rate.
+//!
+//! Systems that move things physically should be added with
+//! [add_physics_system](PhysicsAppExt::add_physics_system) and use [PHYSICS_DELTA] instead of
+//! [Time].
+//! Rapier's own systems are stepped in the stages that follow them.
+//!
+//! Since the fixed timestep doesn't line up with frames, the [Transform]s of moving bodies are
+//! interpolated between the last two steps for rendering.
+//! See [InterpolatedTransform].
+use bevy::{ecs::schedule::IntoSystemDescriptor, prelude::*, transform::TransformSystem};
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::fixedtimestep::{FixedTimestepInfo, FixedTimestepStage};
+use std::time::Duration;
+
+/// Label of the fixed timestep stage.
+pub const PHYSICS_TIMESTEP: &str = "physics_timestep";
+
+/// Seconds that pass in every step of the [PHYSICS_TIMESTEP].
+pub const PHYSICS_DELTA: f32 = 1. / 60.;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub struct FixedTimestepPlugin;
+
+impl Plugin for FixedTimestepPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RapierConfiguration {
+            gravity: Vec2::ZERO,
+            timestep_mode: TimestepMode::Fixed {
+                dt: PHYSICS_DELTA,
+                substeps: 1,
+            },
+            ..default()
+        })
+        .init_resource::<PhysicsSchedule>()
+        .init_resource::<LastPhysicsStep>()
+        .add_stage_before(
+            CoreStage::Update,
+            PHYSICS_TIMESTEP,
+            FixedTimestepStage::from_stage(
+                Duration::from_secs_f32(PHYSICS_DELTA),
+                RunPhysicsSchedule,
+            ),
+        )
+        .add_system_set_to_stage(
+            CoreStage::Last,
+            RapierPhysicsPlugin::<NoUserData>::get_systems(PhysicsStages::DetectDespawn),
+        )
+        .add_physics_system(
+            restore_interpolated_transforms.label("restore_interpolated_transforms"),
+        )
+        .add_system(add_interpolated_transforms.label("add_interpolated_transforms"))
+        .add_system_to_stage(
+            CoreStage::PostUpdate,
+            interpolate_transforms.before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+pub trait PhysicsAppExt {
+    /// Adds a system that runs once every physics step, before rapier steps the simulation.
+    fn add_physics_system<Params>(
+        &mut self,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut Self;
+}
+
+impl PhysicsAppExt for App {
+    fn add_physics_system<Params>(
+        &mut self,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(PhysicsSchedule::default)
+            .0
+            .add_system_to_stage(PhysicsStage::Gameplay, system);
+        self
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, StageLabel)]
+enum PhysicsStage {
+    Gameplay,
+    SyncBackend,
+    StepSimulation,
+    Writeback,
+    Record,
+}
+
+/// Stages run every physics step.
+///
+/// They're kept in a resource so that plugins can add to them after the [FixedTimestepStage] is
+/// built.
+struct PhysicsSchedule(Schedule);
+
+impl Default for PhysicsSchedule {
+    fn default() -> Self {
+        let rapier_systems = RapierPhysicsPlugin::<NoUserData>::get_systems;
+
+        PhysicsSchedule(
+            Schedule::default()
+                .with_stage(PhysicsStage::Gameplay, SystemStage::parallel())
+                .with_stage(
+                    PhysicsStage::SyncBackend,
+                    SystemStage::parallel()
+                        .with_system_set(rapier_systems(PhysicsStages::SyncBackend)),
+                )
+                .with_stage(
+                    PhysicsStage::StepSimulation,
+                    SystemStage::parallel()
+                        .with_system_set(rapier_systems(PhysicsStages::StepSimulation)),
+                )
+                .with_stage(
+                    PhysicsStage::Writeback,
+                    SystemStage::parallel()
+                        .with_system_set(rapier_systems(PhysicsStages::Writeback)),
+                )
+                .with_stage(
+                    PhysicsStage::Record,
+                    SystemStage::parallel()
+                        .with_system(record_interpolated_transforms)
+                        .with_system(record_last_physics_step),
+                ),
+        )
+    }
+}
+
+struct RunPhysicsSchedule;
+
+impl Stage for RunPhysicsSchedule {
+    fn run(&mut self, world: &mut World) {
+        world.resource_scope(|world, mut schedule: Mut<PhysicsSchedule>| schedule.0.run(world));
+    }
+}
+
+/// Time left over in the fixed timestep after the last physics step, and when that step ran.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+struct LastPhysicsStep {
+    remaining: Duration,
+    time_since_startup: Duration,
+}
+
+/// Tracks the translation of a moving body at the last two physics steps.
+///
+/// Its [Transform] is the latest step's while physics runs, and a blend of the two otherwise.
+#[derive(Copy, Clone, PartialEq, Debug, Default, Component)]
+pub struct InterpolatedTransform {
+    previous: Vec3,
+    current: Vec3,
+}
+
+impl InterpolatedTransform {
+    pub fn new(translation: Vec3) -> Self {
+        InterpolatedTransform {
+            previous: translation,
+            current: translation,
+        }
+    }
+
+    /// Moves straight to `translation`, without blending from the old one.
+    ///
+    /// Use this alongside setting the [Transform] when teleporting a body.
+    pub fn teleport(&mut self, translation: Vec3) {
+        *self = InterpolatedTransform::new(translation);
+    }
+}
+
+/// Gives every non-fixed rigid body an [InterpolatedTransform].
+fn add_interpolated_transforms(
+    mut commands: Commands,
+    bodies: Query<(Entity, &Transform, &RigidBody), Added<RigidBody>>,
+) {
+    for (entity, transform, rigid_body) in bodies.iter() {
+        if *rigid_body != RigidBody::Fixed {
+            commands
+                .entity(entity)
+                .insert(InterpolatedTransform::new(transform.translation));
+        }
+    }
+}
+
+/// Puts bodies back where the physics left them, before the next step.
+///
+/// Their [GlobalTransform]s are updated too, since scene queries and rapier depend on them.
+fn restore_interpolated_transforms(
+    mut bodies: Query<(&mut Transform, &mut GlobalTransform, &InterpolatedTransform)>,
+) {
+    for (mut transform, mut global_transform, interpolated) in bodies.iter_mut() {
+        let offset = interpolated.current - transform.translation;
+
+        if offset != Vec3::ZERO {
+            transform.translation = interpolated.current;
+            *global_transform = GlobalTransform::from_translation(offset) * *global_transform;
+        }
+    }
+}
+
+fn record_interpolated_transforms(mut bodies: Query<(&Transform, &mut InterpolatedTransform)>) {
+    for (transform, mut interpolated) in bodies.iter_mut() {
+        interpolated.previous = interpolated.current;
+        interpolated.current = transform.translation;
+    }
+}
+
+fn record_last_physics_step(
+    info: Res<FixedTimestepInfo>,
+    time: Res<Time>,
+    mut last_physics_step: ResMut<LastPhysicsStep>,
+) {
+    *last_physics_step = LastPhysicsStep {
+        remaining: info.remaining(),
+        time_since_startup: time.time_since_startup(),
+    };
+}
+
+fn interpolate_transforms(
+    last_physics_step: Res<LastPhysicsStep>,
+    time: Res<Time>,
+    mut bodies: Query<(&mut Transform, &InterpolatedTransform)>,
+) {
+    // Frames that didn't step the physics still count towards the next step
+    let since_last_step = time.time_since_startup() - last_physics_step.time_since_startup;
+    let overstep =
+        ((last_physics_step.remaining + since_last_step).as_secs_f32() / PHYSICS_DELTA).min(1.);
+
+    for (mut transform, interpolated) in bodies.iter_mut() {
+        transform.translation = interpolated.previous.lerp(interpolated.current, overstep);
+    }
+}
@@ -1,4 +1,12 @@
-use crate::{animation::SpriteSheetAnimation, from_component::*, player::*, GameState};
+use crate::{
+    animation::SpriteSheetAnimation,
+    from_component::*,
+    history::TimeSinceLevelStart,
+    ldtk_fields::{entity_refs, field_value},
+    player::*,
+    run_stats::{LevelComplete, RunStats},
+    AssetHolder, GameState,
+};
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
@@ -9,7 +17,7 @@ pub struct GoalPlugin;
 impl Plugin for GoalPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(FromComponentPlugin::<Goal, SpriteSheetAnimation>::new())
-            .add_system(victory.run_in_state(GameState::Gameplay))
+            .add_system(victory.run_in_state(GameState::Gameplay).label("victory"))
             .register_ldtk_entity::<GoalBundle>("Goal");
     }
 }
@@ -17,6 +25,69 @@ impl Plugin for GoalPlugin {
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
 pub struct Goal;
 
+/// The level a [Goal] leads to, by identifier or iid, or [None] for the next level.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct GoalTarget(pub Option<String>);
+
+impl From<EntityInstance> for GoalTarget {
+    fn from(entity_instance: EntityInstance) -> Self {
+        GoalTarget(match field_value(&entity_instance, "Target") {
+            Some(FieldValue::String(Some(target))) if !target.is_empty() => Some(target.clone()),
+            _ => None,
+        })
+    }
+}
+
+impl GoalTarget {
+    /// Index of the level this leads to, from a goal in the level at `level_index`.
+    ///
+    /// Targets that don't name any level fall back to the next one.
+    pub fn level_index(&self, ldtk_asset: &LdtkAsset, level_index: usize) -> usize {
+        self.0
+            .as_ref()
+            .and_then(|target| {
+                ldtk_asset
+                    .iter_levels()
+                    .position(|level| &level.identifier == target || &level.iid == target)
+            })
+            .unwrap_or(level_index + 1)
+    }
+}
+
+/// Iids of the keys that must be held, and doors that must be open, before a [Goal] can be reached.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct GoalRequirements(pub Vec<String>);
+
+impl From<EntityInstance> for GoalRequirements {
+    fn from(entity_instance: EntityInstance) -> Self {
+        GoalRequirements(entity_refs(&entity_instance, "Requires"))
+    }
+}
+
+/// Whether a [Goal] is still waiting on its [GoalRequirements], so touching it does nothing.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct Locked(pub bool);
+
+/// Indices of the levels that the goals in the level at `level_index` lead to, in order.
+pub fn level_branches(ldtk_asset: &LdtkAsset, level_index: usize) -> Vec<usize> {
+    let mut branches: Vec<usize> = ldtk_asset
+        .iter_levels()
+        .nth(level_index)
+        .and_then(|level| level.layer_instances.as_ref())
+        .into_iter()
+        .flatten()
+        .flat_map(|layer| layer.entity_instances.iter())
+        .filter(|entity_instance| entity_instance.identifier == "Goal")
+        .map(|entity_instance| {
+            GoalTarget::from(entity_instance.clone()).level_index(ldtk_asset, level_index)
+        })
+        .collect();
+
+    branches.sort_unstable();
+    branches.dedup();
+    branches
+}
+
 #[derive(Clone, Default, Bundle, LdtkEntity)]
 pub struct GoalBundle {
     #[sprite_sheet_bundle]
@@ -24,6 +95,11 @@ pub struct GoalBundle {
     pub sprite_sheet_bundle: SpriteSheetBundle,
     pub goal: Goal,
     #[from_entity_instance]
+    pub target: GoalTarget,
+    #[from_entity_instance]
+    pub requirements: GoalRequirements,
+    pub locked: Locked,
+    #[from_entity_instance]
     #[bundle]
     pub collider_bundle: ColliderBundle,
     pub sensor: Sensor,
@@ -39,28 +115,74 @@ impl From<Goal> for SpriteSheetAnimation {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn victory(
     mut commands: Commands,
-    player_query: Query<&Vitality, With<Player>>,
-    goal_query: Query<Entity, With<Goal>>,
+    player_query: Query<(Entity, &Vitality), With<Player>>,
+    goal_query: Query<(&GoalTarget, &Locked), With<Goal>>,
+    unlocked_goal_query: Query<Entity, (With<Goal>, Changed<Locked>)>,
     mut collision_events: EventReader<CollisionEvent>,
+    rapier_context: Res<RapierContext>,
     mut level_selection: ResMut<LevelSelection>,
+    asset_holder: Res<AssetHolder>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    mut level_complete_events: EventWriter<LevelComplete>,
+    run_stats: Res<RunStats>,
+    time_since_level_start: Res<TimeSinceLevelStart>,
+    time: Res<Time>,
 ) {
-    for collision in collision_events.iter() {
-        match collision {
-            CollisionEvent::Started(a, b, _) => {
-                if player_query.contains(*a) && goal_query.contains(*b)
-                    || player_query.contains(*b) && goal_query.contains(*a)
-                {
-                    if *player_query.single() == Vitality::Alive {
-                        if let LevelSelection::Index(level_index) = *level_selection {
-                            *level_selection = LevelSelection::Index(level_index + 1);
-                            commands.insert_resource(NextState(GameState::Preamble));
-                        }
-                    }
-                }
-            }
-            _ => (),
+    let touched_goals: Vec<Entity> = collision_events
+        .iter()
+        .filter_map(|collision| match collision {
+            CollisionEvent::Started(a, b, _) if player_query.contains(*a) => Some(*b),
+            CollisionEvent::Started(a, b, _) if player_query.contains(*b) => Some(*a),
+            _ => None,
+        })
+        .collect();
+
+    let (player_entity, vitality) = match player_query.get_single() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    // The player doesn't have to touch a goal again if it unlocks while they're inside it
+    let unlocked_goals = unlocked_goal_query.iter().filter(|goal_entity| {
+        rapier_context.intersection_pair(player_entity, *goal_entity) == Some(true)
+    });
+
+    // Locked goals can't be reached until the player has what they need
+    let reached_target = touched_goals
+        .into_iter()
+        .chain(unlocked_goals)
+        .find_map(|goal_entity| match goal_query.get(goal_entity) {
+            Ok((target, Locked(false))) => Some(target),
+            _ => None,
+        });
+
+    if let (Some(target), Vitality::Alive) = (reached_target, vitality) {
+        if let LevelSelection::Index(level_index) = *level_selection {
+            let ldtk_asset = ldtk_assets.get(&asset_holder.ldtk);
+
+            level_complete_events.send(LevelComplete {
+                level_index,
+                level_iid: ldtk_asset
+                    .and_then(|ldtk_asset| ldtk_asset.get_level(&level_selection))
+                    .map(|level| level.iid.clone())
+                    .unwrap_or_default(),
+                level_time: time_since_level_start.0,
+                real_time: time.time_since_startup() - run_stats.started,
+                deaths: run_stats.deaths,
+                restarts: run_stats.restarts,
+                rewinds: run_stats.rewinds,
+                fast_forwards: run_stats.fast_forwards,
+            });
+
+            let next_index = ldtk_asset
+                .map(|ldtk_asset| target.level_index(ldtk_asset, level_index))
+                .unwrap_or(level_index + 1);
+
+            *level_selection = LevelSelection::Index(next_index);
+            commands.insert_resource(NextState(GameState::Preamble));
         }
     }
 }
@@ -0,0 +1,80 @@
+use crate::{
+    player::{ColliderBundle, DeathCause, Player, Vitality},
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(hazard_death.run_in_state(GameState::Gameplay))
+            .register_ldtk_int_cell::<HazardBundle>(2)
+            .register_ldtk_int_cell::<HazardBundle>(3);
+    }
+}
+
+/// Tiles and entities that kill the player on touch.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub enum Hazard {
+    #[default]
+    Spikes,
+    Lava,
+    Pendulum,
+}
+
+impl From<IntGridCell> for Hazard {
+    fn from(int_grid_cell: IntGridCell) -> Hazard {
+        match int_grid_cell.value {
+            3 => Hazard::Lava,
+            _ => Hazard::Spikes,
+        }
+    }
+}
+
+impl From<EntityInstance> for Hazard {
+    fn from(entity_instance: EntityInstance) -> Hazard {
+        match entity_instance.identifier.as_ref() {
+            "Pendulum" => Hazard::Pendulum,
+            _ => Hazard::Spikes,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
+pub struct HazardBundle {
+    #[from_int_grid_cell]
+    pub hazard: Hazard,
+    #[from_int_grid_cell]
+    #[bundle]
+    pub collider_bundle: ColliderBundle,
+    pub sensor: Sensor,
+}
+
+fn hazard_death(
+    mut player_query: Query<&mut Vitality, With<Player>>,
+    hazard_query: Query<Entity, With<Hazard>>,
+    mut collision_events: EventReader<CollisionEvent>,
+) {
+    for collision in collision_events.iter() {
+        if let CollisionEvent::Started(a, b, _) = collision {
+            let player_entity = if hazard_query.contains(*a) {
+                b
+            } else if hazard_query.contains(*b) {
+                a
+            } else {
+                continue;
+            };
+
+            if let Ok(mut vitality) = player_query.get_mut(*player_entity) {
+                if *vitality == Vitality::Alive {
+                    *vitality = Vitality::Dead(DeathCause::Hazard);
+                }
+            }
+        }
+    }
+}
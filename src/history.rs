@@ -1,6 +1,14 @@
-use crate::{level_transition::LevelStart, player::Vitality, GameState};
+use crate::{
+    checkpoint::ReachedCheckpoint,
+    fixed_timestep::{PhysicsAppExt, PHYSICS_DELTA},
+    level_transition::LevelStart,
+    player::{player_touched, DeathCause, Player, Vitality},
+    run_stats::RunStats,
+    GameState,
+};
 use bevy::prelude::*;
 use bevy_ecs_ldtk::{LdtkLevel, Respawn};
+use bevy_rapier2d::prelude::*;
 use iyes_loopless::prelude::*;
 use std::time::Duration;
 
@@ -11,6 +19,7 @@ pub struct HistoryPlugin;
 impl Plugin for HistoryPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(EventSchedulerPlugin::<TimeEvent>::new())
+            .add_event::<RewindTo>()
             .insert_resource(TimeScale(1.))
             .insert_resource(TimeSinceLevelStart(0.))
             .add_system(rewind.run_in_state(GameState::Gameplay))
@@ -24,9 +33,15 @@ impl Plugin for HistoryPlugin {
             //dbg!(time_since_level_start);
             //})
             .add_system(
-                update_time
+                reset_time
                     .run_in_state(GameState::Gameplay)
                     .label("update_time"),
+            )
+            .add_physics_system(
+                advance_time
+                    .run_in_state(GameState::Gameplay)
+                    .label("advance_time")
+                    .after("restore_interpolated_transforms"),
             );
     }
 }
@@ -42,6 +57,28 @@ pub struct History<T> {
     pub moments: Vec<Moment<T>>,
 }
 
+impl<T: Copy + Default> History<T> {
+    /// Forgets moments that haven't happened yet at `timestamp`, returning the latest one left.
+    pub fn rewind_to(&mut self, timestamp: f32) -> T {
+        while self
+            .moments
+            .last()
+            .map(|m| m.timestamp > timestamp)
+            .unwrap_or(false)
+        {
+            self.moments.pop();
+        }
+
+        self.moments.last().map(|m| m.data).unwrap_or_default()
+    }
+}
+
+/// A component that changes when the player touches its entity, like collectibles and switches.
+pub trait Touchable: Component + Copy + Default + PartialEq {
+    /// What this becomes after being touched.
+    fn touched(self) -> Self;
+}
+
 #[derive(Copy, Clone, PartialEq, Debug, Default)]
 pub struct TimeSinceLevelStart(pub f32);
 
@@ -54,6 +91,13 @@ pub enum TimeEvent {
     Normal,
 }
 
+/// Event that jumps the level's timeline straight back to the given [TimeSinceLevelStart].
+///
+/// Anything with a [History] should restore itself to how it was at that timestamp.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RewindTo(pub f32);
+
+#[allow(clippy::too_many_arguments)]
 pub fn rewind(
     mut commands: Commands,
     input: Res<Input<KeyCode>>,
@@ -62,10 +106,14 @@ pub fn rewind(
     vitality: Query<&Vitality>,
     levels: Query<Entity, With<Handle<LdtkLevel>>>,
     mut level_start_events: EventWriter<LevelStart>,
+    mut rewind_to_events: EventWriter<RewindTo>,
+    reached_checkpoint: Res<ReachedCheckpoint>,
+    mut run_stats: ResMut<RunStats>,
 ) {
     if input.just_pressed(KeyCode::Z) && time_scale.0 == 1. && *vitality.single() == Vitality::Alive
     {
         *time_scale = TimeScale(-10.);
+        run_stats.rewinds += 1;
         rewind_event_scheduler.schedule(TimeEvent::Rewind, Duration::ZERO);
         rewind_event_scheduler.schedule(TimeEvent::Normal, Duration::from_millis(200));
     } else if input.just_pressed(KeyCode::X)
@@ -73,13 +121,20 @@ pub fn rewind(
         && *vitality.single() == Vitality::Alive
     {
         *time_scale = TimeScale(10.);
+        run_stats.fast_forwards += 1;
         rewind_event_scheduler.schedule(TimeEvent::FastForward, Duration::ZERO);
         rewind_event_scheduler.schedule(TimeEvent::Normal, Duration::from_millis(200));
     }
 
     if input.just_pressed(KeyCode::R) {
-        commands.entity(levels.single()).insert(Respawn);
-        level_start_events.send(LevelStart);
+        run_stats.restarts += 1;
+
+        if let Some(checkpoint) = reached_checkpoint.0 {
+            rewind_to_events.send(RewindTo(checkpoint.timestamp));
+        } else {
+            commands.entity(levels.single()).insert(Respawn);
+            level_start_events.send(LevelStart);
+        }
     }
 }
 
@@ -94,24 +149,38 @@ pub fn stop_rewind(mut rewind_events: EventReader<TimeEvent>, mut time_scale: Re
     }
 }
 
-pub fn update_time(
+/// Sets the time back to the start of the level, or wherever the timeline jumped back to.
+pub fn reset_time(
     mut time_scale: ResMut<TimeScale>,
     mut time_since_level_start: ResMut<TimeSinceLevelStart>,
-    bevy_time: Res<Time>,
     mut level_events: EventReader<LevelStart>,
+    mut rewind_to_events: EventReader<RewindTo>,
     mut time_events: EventWriter<TimeEvent>,
     mut vitals: Query<&mut Vitality>,
 ) {
-    for _ in level_events.iter() {
+    let restarts = level_events.iter().map(|_| 0.).chain(
+        rewind_to_events
+            .iter()
+            .map(|RewindTo(timestamp)| *timestamp),
+    );
+
+    for timestamp in restarts {
         time_scale.0 = 1.;
-        time_since_level_start.0 = 0.;
+        time_since_level_start.0 = timestamp;
         time_events.send(TimeEvent::Normal);
         for mut vitality in vitals.iter_mut() {
             *vitality = Vitality::Alive;
         }
     }
+}
 
-    time_since_level_start.0 = time_since_level_start.0 + bevy_time.delta_seconds() * time_scale.0;
+/// Moves the level's time along with the physics, so that anything derived from it stays in step.
+pub fn advance_time(
+    mut time_scale: ResMut<TimeScale>,
+    mut time_since_level_start: ResMut<TimeSinceLevelStart>,
+    mut vitals: Query<&mut Vitality>,
+) {
+    time_since_level_start.0 += PHYSICS_DELTA * time_scale.0;
 
     if time_since_level_start.0 < 0. {
         time_since_level_start.0 = 0.;
@@ -121,7 +190,52 @@ pub fn update_time(
         time_since_level_start.0 = 10.;
         time_scale.0 = 0.;
         for mut vitality in vitals.iter_mut() {
-            *vitality = Vitality::Dead;
+            if *vitality == Vitality::Alive {
+                *vitality = Vitality::Dead(DeathCause::Impact);
+            }
+        }
+    }
+}
+
+/// Updates the [Touchable] `T` of anything the player touches, remembering when in its [History].
+pub fn record_touches<T: Touchable>(
+    player_query: Query<&Vitality, With<Player>>,
+    mut touchable_query: Query<(&mut T, &mut History<T>)>,
+    mut collision_events: EventReader<CollisionEvent>,
+    time_scale: Res<TimeScale>,
+    time_since_level_start: Res<TimeSinceLevelStart>,
+) {
+    for collision in collision_events.iter() {
+        let touched = player_touched(collision, &player_query, &time_scale)
+            .and_then(|entity| touchable_query.get_mut(entity).ok());
+
+        if let Some((mut value, mut history)) = touched {
+            let new_value = value.touched();
+
+            if *value != new_value {
+                *value = new_value;
+
+                history.moments.push(Moment {
+                    timestamp: time_since_level_start.0,
+                    data: new_value,
+                });
+            }
+        }
+    }
+}
+
+/// Sets every `T` back to the latest value in its [History], dropping any later ones.
+///
+/// This only changes anything when the level's time has gone backwards.
+pub fn rewind_history<T: Component + Copy + Default + PartialEq>(
+    mut query: Query<(&mut T, &mut History<T>)>,
+    time_since_level_start: Res<TimeSinceLevelStart>,
+) {
+    for (mut value, mut history) in query.iter_mut() {
+        let rewound = history.rewind_to(time_since_level_start.0);
+
+        if *value != rewound {
+            *value = rewound;
         }
     }
 }
@@ -0,0 +1,209 @@
+//! Keys that open the doors they point at, and goals that need keys held or doors opened.
+//!
+//! The player keeps hold of a key until time is rewound past when they grabbed it, which puts it
+//! back where it was and shuts its doors again.
+use crate::{
+    goals::{Goal, GoalRequirements, Locked},
+    history::{record_touches, rewind_history, History, Touchable},
+    ldtk_fields::{entity_refs, EntityIid},
+    player::ColliderBundle,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+
+pub struct KeyPlugin;
+
+impl Plugin for KeyPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<KeyBundle>("Key")
+            .register_ldtk_entity::<DoorBundle>("Door")
+            .add_system(
+                record_touches::<Held>
+                    .run_in_state(GameState::Gameplay)
+                    .label("pick_up_keys")
+                    .after("update_time"),
+            )
+            .add_system(
+                rewind_history::<Held>
+                    .run_in_state(GameState::Gameplay)
+                    .label("rewind_keys")
+                    .after("update_time"),
+            )
+            .add_system(
+                open_doors
+                    .run_in_state(GameState::Gameplay)
+                    .after("pick_up_keys")
+                    .after("rewind_keys"),
+            )
+            .add_system(
+                lock_goals
+                    .run_in_state(GameState::Gameplay)
+                    .after("pick_up_keys")
+                    .after("rewind_keys")
+                    .before("victory"),
+            )
+            .add_system(show_keys.run_in_state(GameState::Gameplay))
+            .add_system(show_doors.run_in_state(GameState::Gameplay))
+            .add_system(show_locked_goals.run_in_state(GameState::Gameplay));
+    }
+}
+
+/// Opacity of a door once it's been opened.
+const OPEN_DOOR_ALPHA: f32 = 0.25;
+
+const LOCKED_GOAL_COLOR: Color = Color::rgb(0.4, 0.4, 0.4);
+
+/// Whether a [Key] is being carried by the player.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct Held(pub bool);
+
+impl Touchable for Held {
+    fn touched(self) -> Self {
+        Held(true)
+    }
+}
+
+/// Iids of the doors a key opens.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct KeyDoors(pub Vec<String>);
+
+impl From<EntityInstance> for KeyDoors {
+    fn from(entity_instance: EntityInstance) -> Self {
+        KeyDoors(entity_refs(&entity_instance, "Doors"))
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct Key;
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct KeyBundle {
+    #[sprite_sheet_bundle]
+    #[bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    pub key: Key,
+    #[from_entity_instance]
+    pub iid: EntityIid,
+    #[from_entity_instance]
+    pub doors: KeyDoors,
+    pub held: Held,
+    pub history: History<Held>,
+    #[from_entity_instance]
+    #[bundle]
+    pub collider_bundle: ColliderBundle,
+    pub sensor: Sensor,
+}
+
+/// Whether a [Door] has been opened by a key that's being held.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct DoorOpen(pub bool);
+
+/// Blocks the way until the player holds a [Key] pointing at it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct Door;
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct DoorBundle {
+    #[sprite_sheet_bundle]
+    #[bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    pub door: Door,
+    #[from_entity_instance]
+    pub iid: EntityIid,
+    pub open: DoorOpen,
+    #[from_entity_instance]
+    #[bundle]
+    pub collider_bundle: ColliderBundle,
+}
+
+/// Opens doors while a key pointing at them is held, turning them into sensors.
+fn open_doors(
+    mut commands: Commands,
+    key_query: Query<(&KeyDoors, &Held), With<Key>>,
+    mut door_query: Query<(Entity, &EntityIid, &mut DoorOpen), With<Door>>,
+) {
+    for (entity, iid, mut door_open) in door_query.iter_mut() {
+        let open = key_query
+            .iter()
+            .any(|(doors, held)| held.0 && doors.0.contains(&iid.0));
+
+        if door_open.0 != open {
+            *door_open = DoorOpen(open);
+
+            if open {
+                commands.entity(entity).insert(Sensor);
+            } else {
+                commands.entity(entity).remove::<Sensor>();
+            }
+        }
+    }
+}
+
+/// Locks goals until every key they require is held and every door they require is open.
+///
+/// Requirements that don't point at a key or door are ignored.
+fn lock_goals(
+    mut goal_query: Query<(&GoalRequirements, &mut Locked), With<Goal>>,
+    key_query: Query<(&EntityIid, &Held), With<Key>>,
+    door_query: Query<(&EntityIid, &DoorOpen), With<Door>>,
+) {
+    for (requirements, mut locked) in goal_query.iter_mut() {
+        let is_met = |required: &String| {
+            key_query
+                .iter()
+                .map(|(iid, held)| (iid, held.0))
+                .chain(door_query.iter().map(|(iid, open)| (iid, open.0)))
+                .all(|(iid, unlocked)| unlocked || &iid.0 != required)
+        };
+
+        let now_locked = Locked(!requirements.0.iter().all(is_met));
+
+        if *locked != now_locked {
+            *locked = now_locked;
+        }
+    }
+}
+
+type ChangedKeys<'w, 's> =
+    Query<'w, 's, (&'static Held, &'static mut Visibility), (With<Key>, Changed<Held>)>;
+
+fn show_keys(mut key_query: ChangedKeys) {
+    for (held, mut visibility) in key_query.iter_mut() {
+        visibility.is_visible = !held.0;
+    }
+}
+
+type ChangedDoors<'w, 's> = Query<
+    'w,
+    's,
+    (&'static DoorOpen, &'static mut TextureAtlasSprite),
+    (With<Door>, Changed<DoorOpen>),
+>;
+
+fn show_doors(mut door_query: ChangedDoors) {
+    for (door_open, mut sprite) in door_query.iter_mut() {
+        sprite
+            .color
+            .set_a(if door_open.0 { OPEN_DOOR_ALPHA } else { 1. });
+    }
+}
+
+type ChangedGoals<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Locked, &'static mut TextureAtlasSprite),
+    (With<Goal>, Changed<Locked>),
+>;
+
+fn show_locked_goals(mut goal_query: ChangedGoals) {
+    for (locked, mut sprite) in goal_query.iter_mut() {
+        sprite.color = if locked.0 {
+            LOCKED_GOAL_COLOR
+        } else {
+            Color::WHITE
+        };
+    }
+}
@@ -0,0 +1,50 @@
+//! Helpers for reading the custom fields of LDtk entity instances.
+use bevy::prelude::*;
+use bevy_ecs_ldtk::{prelude::*, utils::ldtk_pixel_coords_to_translation_pivoted};
+
+/// The iid of the LDtk entity instance something was spawned from.
+///
+/// Lets other entities find it through their EntityRef fields.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct EntityIid(pub String);
+
+impl From<EntityInstance> for EntityIid {
+    fn from(entity_instance: EntityInstance) -> Self {
+        EntityIid(entity_instance.iid)
+    }
+}
+
+pub fn field_value<'a>(
+    entity_instance: &'a EntityInstance,
+    identifier: &str,
+) -> Option<&'a FieldValue> {
+    entity_instance
+        .field_instances
+        .iter()
+        .find(|f| f.identifier == identifier)
+        .map(|f| &f.value)
+}
+
+/// Iids of the entities an EntityRef field points at, whether it's an array or not.
+pub fn entity_refs(entity_instance: &EntityInstance, identifier: &str) -> Vec<String> {
+    match field_value(entity_instance, identifier) {
+        Some(FieldValue::EntityRefs(refs)) => refs
+            .iter()
+            .flatten()
+            .map(|r| r.entity_iid.clone())
+            .collect(),
+        Some(FieldValue::EntityRef(Some(r))) => vec![r.entity_iid.clone()],
+        _ => Vec::new(),
+    }
+}
+
+/// Translation of the center of an entity instance, which is where bevy_ecs_ldtk puts it.
+pub fn entity_center(entity_instance: &EntityInstance, layer_instance: &LayerInstance) -> Vec3 {
+    ldtk_pixel_coords_to_translation_pivoted(
+        entity_instance.px,
+        layer_instance.c_hei * layer_instance.grid_size,
+        IVec2::new(entity_instance.width, entity_instance.height),
+        entity_instance.pivot,
+    )
+    .extend(0.)
+}
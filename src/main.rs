@@ -1,18 +1,32 @@
 mod animation;
 mod apophis;
 mod camera;
+mod character_controller;
+mod checkpoint;
+mod dash;
 mod event_scheduler;
+mod fixed_timestep;
 mod from_component;
 mod goals;
+mod hazard;
 mod history;
+mod key;
+mod ldtk_fields;
 mod level_transition;
 mod music;
+mod orbit;
+mod path_preview;
 mod pause;
+mod phase_wall;
 mod platform;
 mod player;
 mod previous_component;
+mod run_stats;
+mod switch;
+mod time_shard;
 mod ui;
 mod wall;
+mod wall_merge;
 
 use bevy::{prelude::*, render::texture::ImageSettings};
 use bevy_asset_loader::prelude::*;
@@ -49,20 +63,31 @@ fn main() {
             int_grid_rendering: IntGridRendering::Colorful,
             ..default()
         })
-        .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
+        .add_plugin(
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0)
+                .with_default_system_setup(false),
+        )
         //.add_plugin(RapierDebugRenderPlugin::default())
-        .insert_resource(RapierConfiguration {
-            gravity: Vec2::ZERO,
-            ..default()
-        })
+        .add_plugin(fixed_timestep::FixedTimestepPlugin)
         .add_plugin(wall::WallPlugin)
+        .add_plugin(hazard::HazardPlugin)
         .add_plugin(player::PlayerPlugin)
+        .add_plugin(character_controller::CharacterControllerPlugin)
+        .add_plugin(dash::DashPlugin)
         .add_plugin(camera::CameraPlugin)
         .add_plugin(level_transition::LevelTransitionPlugin)
         .add_plugin(history::HistoryPlugin)
         .add_plugin(platform::PlatformPlugin)
+        .add_plugin(switch::SwitchPlugin)
+        .add_plugin(orbit::OrbitPlugin)
+        .add_plugin(phase_wall::PhaseWallPlugin)
+        .add_plugin(path_preview::PathPreviewPlugin)
         .add_plugin(animation::SpriteSheetAnimationPlugin)
         .add_plugin(goals::GoalPlugin)
+        .add_plugin(checkpoint::CheckpointPlugin)
+        .add_plugin(time_shard::TimeShardPlugin)
+        .add_plugin(key::KeyPlugin)
+        .add_plugin(run_stats::RunStatsPlugin)
         .add_plugin(music::MusicPlugin)
         .add_plugin(ui::UiPlugin)
         .add_plugin(pause::PausePlugin)
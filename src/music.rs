@@ -1,6 +1,7 @@
 use crate::{
     history::{TimeEvent, TimeScale, TimeSinceLevelStart},
     player::Vitality,
+    run_stats::LevelComplete,
     AssetHolder, GameState,
 };
 use bevy::prelude::*;
@@ -13,7 +14,7 @@ impl Plugin for MusicPlugin {
     fn build(&self, app: &mut App) {
         app.add_system(play_music.run_on_event::<TimeEvent>().after("update_time"))
             .add_system(death_sound.run_in_state(GameState::Gameplay))
-            .add_enter_system(GameState::Preamble, victory_sound);
+            .add_system(victory_sound.run_on_event::<LevelComplete>());
     }
 }
 
@@ -50,7 +51,7 @@ fn death_sound(
     asset_holder: Res<AssetHolder>,
     audio: Res<bevy_kira_audio::Audio>,
 ) {
-    if query.iter().any(|v| *v == Vitality::Dead) {
+    if query.iter().any(|v| matches!(v, Vitality::Dead(_))) {
         audio.stop();
         audio.play(asset_holder.death.clone());
     }
@@ -0,0 +1,251 @@
+//! Bodies that go round or swing about a pivot, like rotating platforms and pendulums.
+//!
+//! Their angle only depends on [TimeSinceLevelStart], so they rewind along with everything else.
+use crate::{
+    fixed_timestep::{InterpolatedTransform, PhysicsAppExt, PHYSICS_DELTA},
+    hazard::Hazard,
+    history::{RewindTo, TimeSinceLevelStart},
+    ldtk_fields::{entity_center, field_value},
+    player::ColliderBundle,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+pub struct OrbitPlugin;
+
+impl Plugin for OrbitPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<RotatingPlatformBundle>("RotatingPlatform")
+            .register_ldtk_entity::<PendulumBundle>("Pendulum")
+            .add_physics_system(
+                orbit_movement
+                    .run_in_state(GameState::Gameplay)
+                    .after("restore_interpolated_transforms")
+                    .after("advance_time"),
+            )
+            .add_system(place_new_orbits.before("add_interpolated_transforms"))
+            .add_system(
+                rewind_orbits_to
+                    .run_in_state(GameState::Gameplay)
+                    .run_on_event::<RewindTo>(),
+            )
+            .add_system(spawn_pendulum_rods)
+            .add_system(move_pendulum_rods.run_in_state(GameState::Gameplay));
+    }
+}
+
+const ROD_COLOR: Color = Color::rgb(0.41, 0.42, 0.42);
+
+const ROD_WIDTH: f32 = 3.;
+
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum OrbitMotion {
+    /// Goes all the way round, turning as it goes so that it always faces the same way from the
+    /// pivot.
+    ///
+    /// With no radius, it spins in place.
+    #[default]
+    Rotate,
+    /// Swings back and forth below the pivot, turning to face away from it.
+    Swing {
+        /// Furthest angle from hanging straight down, in radians.
+        amplitude: f32,
+    },
+}
+
+/// Moves a body round a pivot.
+///
+/// The entity is placed at the pivot, "Radius" is in pixels, "Speed" is in degrees per second,
+/// and "Phase" is in degrees.
+/// Pendulums also have an "Amplitude" in degrees.
+#[derive(Copy, Clone, PartialEq, Debug, Default, Component)]
+pub struct Orbit {
+    pivot: Vec3,
+    radius: f32,
+    /// In radians per second.
+    angular_speed: f32,
+    /// In radians.
+    phase: f32,
+    motion: OrbitMotion,
+}
+
+impl Orbit {
+    /// Angle of the body from the pivot at `timestamp`.
+    ///
+    /// Rotating bodies start to the right of the pivot, and swinging ones start hanging below it.
+    fn angle(&self, timestamp: f32) -> f32 {
+        let cycle = self.phase + self.angular_speed * timestamp;
+
+        match self.motion {
+            OrbitMotion::Rotate => cycle,
+            OrbitMotion::Swing { amplitude } => amplitude * cycle.sin() - PI / 2.,
+        }
+    }
+
+    pub fn position(&self, timestamp: f32) -> Vec3 {
+        let angle = self.angle(timestamp);
+
+        self.pivot + Vec3::new(angle.cos(), angle.sin(), 0.) * self.radius
+    }
+
+    /// Rotation of the body at `timestamp`.
+    pub fn rotation(&self, timestamp: f32) -> Quat {
+        match self.motion {
+            OrbitMotion::Rotate => Quat::from_rotation_z(self.angle(timestamp)),
+            OrbitMotion::Swing { .. } => Quat::from_rotation_z(self.angle(timestamp) + PI / 2.),
+        }
+    }
+}
+
+impl LdtkEntity for Orbit {
+    fn bundle_entity(
+        entity_instance: &EntityInstance,
+        layer_instance: &LayerInstance,
+        _: Option<&Handle<Image>>,
+        _: Option<&TilesetDefinition>,
+        _: &AssetServer,
+        _: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let float = |identifier| match field_value(entity_instance, identifier) {
+            Some(FieldValue::Float(Some(value))) => Some(*value),
+            _ => None,
+        };
+
+        let motion = match float("Amplitude") {
+            Some(amplitude) => OrbitMotion::Swing {
+                amplitude: amplitude.to_radians(),
+            },
+            None => OrbitMotion::Rotate,
+        };
+
+        Orbit {
+            pivot: entity_center(entity_instance, layer_instance),
+            radius: float("Radius").unwrap_or(0.),
+            angular_speed: float("Speed").unwrap_or(0.).to_radians(),
+            phase: float("Phase").unwrap_or(0.).to_radians(),
+            motion,
+        }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct RotatingPlatformBundle {
+    #[sprite_sheet_bundle]
+    #[bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[ldtk_entity]
+    pub orbit: Orbit,
+    #[from_entity_instance]
+    #[bundle]
+    pub collider_bundle: ColliderBundle,
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct PendulumBundle {
+    #[sprite_sheet_bundle]
+    #[bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[ldtk_entity]
+    pub orbit: Orbit,
+    #[from_entity_instance]
+    pub hazard: Hazard,
+    #[from_entity_instance]
+    #[bundle]
+    pub collider_bundle: ColliderBundle,
+    pub sensor: Sensor,
+}
+
+/// Connects a pendulum to its pivot.
+#[derive(Copy, Clone, PartialEq, Debug, Component)]
+struct PendulumRod(Entity);
+
+/// Moves orbiting bodies from their pivot to where they should start.
+fn place_new_orbits(
+    mut query: Query<(&Orbit, &mut Transform), Added<Orbit>>,
+    time_since_level_start: Res<TimeSinceLevelStart>,
+) {
+    for (orbit, mut transform) in query.iter_mut() {
+        transform.translation = orbit.position(time_since_level_start.0);
+        transform.rotation = orbit.rotation(time_since_level_start.0);
+    }
+}
+
+/// Steers orbiting bodies to where they should be by the end of the step.
+fn orbit_movement(
+    mut query: Query<(&Orbit, &Transform, &mut Velocity)>,
+    time_since_level_start: Res<TimeSinceLevelStart>,
+) {
+    for (orbit, transform, mut velocity) in query.iter_mut() {
+        let target = orbit.position(time_since_level_start.0);
+        velocity.linvel = (target - transform.translation).truncate() / PHYSICS_DELTA;
+
+        let target_rotation = orbit.rotation(time_since_level_start.0);
+        let (current_angle, _, _) = transform.rotation.to_euler(EulerRot::ZYX);
+        let (target_angle, _, _) = target_rotation.to_euler(EulerRot::ZYX);
+        // Take the short way round
+        let turn = (target_angle - current_angle + PI).rem_euclid(TAU) - PI;
+        velocity.angvel = turn / PHYSICS_DELTA;
+    }
+}
+
+/// Jumps orbiting bodies straight to where they were at the [RewindTo] timestamp.
+fn rewind_orbits_to(
+    mut rewind_to_events: EventReader<RewindTo>,
+    mut query: Query<(
+        &Orbit,
+        &mut Transform,
+        Option<&mut InterpolatedTransform>,
+        &mut Velocity,
+    )>,
+) {
+    for RewindTo(timestamp) in rewind_to_events.iter() {
+        for (orbit, mut transform, interpolated, mut velocity) in query.iter_mut() {
+            transform.translation = orbit.position(*timestamp);
+            transform.rotation = orbit.rotation(*timestamp);
+            *velocity = Velocity::zero();
+
+            if let Some(mut interpolated) = interpolated {
+                interpolated.teleport(transform.translation);
+            }
+        }
+    }
+}
+
+type NewPendulums<'w, 's> = Query<'w, 's, (Entity, &'static Parent), (Added<Orbit>, With<Hazard>)>;
+
+fn spawn_pendulum_rods(mut commands: Commands, pendulums: NewPendulums) {
+    for (pendulum, parent) in pendulums.iter() {
+        commands.entity(parent.get()).with_children(|layer| {
+            layer
+                .spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color: ROD_COLOR,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .insert(PendulumRod(pendulum));
+        });
+    }
+}
+
+fn move_pendulum_rods(
+    mut rods: Query<(&PendulumRod, &mut Sprite, &mut Transform)>,
+    pendulums: Query<&Orbit>,
+    time_since_level_start: Res<TimeSinceLevelStart>,
+) {
+    for (rod, mut sprite, mut transform) in rods.iter_mut() {
+        if let Ok(orbit) = pendulums.get(rod.0) {
+            let end = orbit.position(time_since_level_start.0);
+
+            sprite.custom_size = Some(Vec2::new(ROD_WIDTH, orbit.radius));
+            // Behind the pendulum
+            transform.translation = orbit.pivot.lerp(end, 0.5) - Vec3::Z * 0.1;
+            transform.rotation = orbit.rotation(time_since_level_start.0);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Shows the routes platforms take, and where they'll be a few seconds from now.
+//!
+//! Levels can turn this on with their "PathPreview" field, and players can turn it on for every
+//! level from the pause menu.
+use crate::{
+    history::TimeSinceLevelStart,
+    ldtk_fields::EntityIid,
+    level_transition::LevelStart,
+    platform::{platform_position, Crumbling, Path, PathClock},
+    switch::{activation, Activated, ActivationTargets},
+    ui::UiAction,
+    AssetHolder, GameState,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use iyes_loopless::prelude::*;
+
+pub struct PathPreviewPlugin;
+
+impl Plugin for PathPreviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PathPreview>()
+            .add_system(enable_path_preview_for_level.run_on_event::<LevelStart>())
+            .add_system(toggle_path_preview.run_in_state(GameState::PauseMenu))
+            .add_system(spawn_path_previews)
+            .add_system(move_platform_ghosts.run_in_state(GameState::Gameplay))
+            .add_system(show_path_previews);
+    }
+}
+
+/// How many seconds ahead of their platforms each ghost is.
+const GHOST_OFFSETS: [f32; 3] = [1., 2., 3.];
+
+const GHOST_ALPHA: f32 = 0.3;
+
+const PATH_COLOR: Color = Color::rgba(1., 1., 1., 0.4);
+
+const PATH_WIDTH: f32 = 2.;
+
+const PATH_POINT_SIZE: f32 = 6.;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct PathPreview {
+    /// Turned on by the level.
+    pub level: bool,
+    /// Turned on by the player from the pause menu.
+    pub assist: bool,
+}
+
+impl PathPreview {
+    pub fn enabled(&self) -> bool {
+        self.level || self.assist
+    }
+}
+
+/// Text of the pause menu button that toggles [PathPreview::assist].
+pub fn path_preview_label(path_preview: &PathPreview) -> String {
+    format!(
+        "Path preview: {}",
+        if path_preview.assist { "on" } else { "off" }
+    )
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct PathPreviewToggleText;
+
+/// A line or point drawn along a platform's path.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+struct PathPreviewPart;
+
+/// A translucent copy of a platform, showing where it will be some seconds ahead.
+#[derive(Copy, Clone, PartialEq, Debug, Component)]
+struct PlatformGhost {
+    platform: Entity,
+    seconds_ahead: f32,
+}
+
+fn enable_path_preview_for_level(
+    mut path_preview: ResMut<PathPreview>,
+    asset_holder: Res<AssetHolder>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    level_selection: Res<LevelSelection>,
+) {
+    path_preview.level = ldtk_assets
+        .get(&asset_holder.ldtk)
+        .and_then(|ldtk_asset| ldtk_asset.get_level(&level_selection))
+        .and_then(|level| {
+            level
+                .field_instances
+                .iter()
+                .find(|f| f.identifier == "PathPreview")
+        })
+        .map(|field| matches!(field.value, FieldValue::Bool(true)))
+        .unwrap_or(false);
+}
+
+fn toggle_path_preview(
+    mut ui_actions: EventReader<UiAction>,
+    mut path_preview: ResMut<PathPreview>,
+    mut toggle_texts: Query<&mut Text, With<PathPreviewToggleText>>,
+) {
+    for action in ui_actions.iter() {
+        if *action == UiAction::TogglePathPreview {
+            path_preview.assist = !path_preview.assist;
+
+            for mut text in toggle_texts.iter_mut() {
+                text.sections[0].value = path_preview_label(&path_preview);
+            }
+        }
+    }
+}
+
+type NewPlatforms<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Path,
+        &'static EntityInstance,
+        &'static Parent,
+        &'static TextureAtlasSprite,
+        &'static Handle<TextureAtlas>,
+    ),
+    Added<Path>,
+>;
+
+/// Draws the path of every new platform alongside it, with its ghosts.
+fn spawn_path_previews(
+    mut commands: Commands,
+    platforms: NewPlatforms,
+    path_preview: Res<PathPreview>,
+) {
+    let is_visible = path_preview.enabled();
+
+    for (platform, path, entity_instance, parent, sprite, texture_atlas) in platforms.iter() {
+        let size = Vec2::new(entity_instance.width as f32, entity_instance.height as f32);
+
+        commands.entity(parent.get()).with_children(|layer| {
+            for (start, end) in path.segments() {
+                let offset = (end - start).truncate();
+
+                layer
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: PATH_COLOR,
+                            custom_size: Some(Vec2::new(offset.length(), PATH_WIDTH)),
+                            ..default()
+                        },
+                        // Behind the platforms
+                        transform: Transform::from_translation(
+                            start.lerp(end, 0.5) - Vec3::Z * 0.2,
+                        )
+                        .with_rotation(Quat::from_rotation_z(offset.y.atan2(offset.x))),
+                        visibility: Visibility { is_visible },
+                        ..default()
+                    })
+                    .insert(PathPreviewPart);
+            }
+
+            for point in path.points() {
+                layer
+                    .spawn_bundle(SpriteBundle {
+                        sprite: Sprite {
+                            color: PATH_COLOR,
+                            custom_size: Some(Vec2::splat(PATH_POINT_SIZE)),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(*point - Vec3::Z * 0.2),
+                        visibility: Visibility { is_visible },
+                        ..default()
+                    })
+                    .insert(PathPreviewPart);
+            }
+
+            for seconds_ahead in GHOST_OFFSETS {
+                layer
+                    .spawn_bundle(SpriteSheetBundle {
+                        sprite: TextureAtlasSprite {
+                            index: sprite.index,
+                            color: Color::rgba(1., 1., 1., GHOST_ALPHA),
+                            custom_size: Some(size),
+                            ..default()
+                        },
+                        texture_atlas: texture_atlas.clone(),
+                        visibility: Visibility { is_visible },
+                        ..default()
+                    })
+                    .insert(PlatformGhost {
+                        platform,
+                        seconds_ahead,
+                    });
+            }
+        });
+    }
+}
+
+fn move_platform_ghosts(
+    mut ghosts: Query<(&PlatformGhost, &mut Transform)>,
+    platforms: Query<(&Path, &EntityIid, &PathClock, &Crumbling)>,
+    activators: Query<(&ActivationTargets, &Activated)>,
+    path_preview: Res<PathPreview>,
+    time_since_level_start: Res<TimeSinceLevelStart>,
+) {
+    if !path_preview.enabled() {
+        return;
+    }
+
+    for (ghost, mut transform) in ghosts.iter_mut() {
+        if let Ok((path, iid, clock, crumbling)) = platforms.get(ghost.platform) {
+            let linked = activation(&iid.0, activators.iter()).is_some();
+            let timestamp = time_since_level_start.0 + ghost.seconds_ahead;
+
+            // Just behind the platforms, but in front of their paths
+            transform.translation =
+                platform_position(path, clock, crumbling, linked, timestamp) - Vec3::Z * 0.1;
+        }
+    }
+}
+
+type PreviewParts<'w, 's> =
+    Query<'w, 's, &'static mut Visibility, Or<(With<PathPreviewPart>, With<PlatformGhost>)>>;
+
+fn show_path_previews(path_preview: Res<PathPreview>, mut parts: PreviewParts) {
+    if path_preview.is_changed() {
+        for mut visibility in parts.iter_mut() {
+            visibility.is_visible = path_preview.enabled();
+        }
+    }
+}
@@ -1,5 +1,11 @@
 use crate::{
-    player::Vitality, previous_component::PreviousComponent, ui::UiAction, AssetHolder, GameState,
+    goals::level_branches,
+    path_preview::{path_preview_label, PathPreview, PathPreviewToggleText},
+    player::Vitality,
+    previous_component::PreviousComponent,
+    time_shard::{level_shard_total, CollectedShards},
+    ui::UiAction,
+    AssetHolder, GameState,
 };
 use bevy::{prelude::*, ui::FocusPolicy};
 use bevy_ecs_ldtk::prelude::*;
@@ -40,12 +46,10 @@ fn spawn_pause_screen(
     asset_holder: Res<AssetHolder>,
     ldtk_assets: Res<Assets<LdtkAsset>>,
     mut rapier_config: ResMut<RapierConfiguration>,
+    path_preview: Res<PathPreview>,
+    collected_shards: Res<CollectedShards>,
 ) {
-    rapier_config.timestep_mode = TimestepMode::Variable {
-        max_dt: 1.0 / 60.0,
-        time_scale: 0.0,
-        substeps: 1,
-    };
+    rapier_config.physics_pipeline_active = false;
 
     commands
         .spawn_bundle(NodeBundle {
@@ -85,12 +89,68 @@ fn spawn_pause_screen(
                 ..default()
             });
 
-            for (level_num, _) in ldtk_assets
-                .get(&asset_holder.ldtk)
-                .unwrap()
-                .iter_levels()
-                .enumerate()
-            {
+            builder
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        position: UiRect {
+                            bottom: Val::Percent(5.),
+                            left: Val::Percent(5.),
+                            ..default()
+                        },
+                        position_type: PositionType::Absolute,
+                        ..default()
+                    },
+                    color: UiColor(Color::WHITE),
+                    ..default()
+                })
+                .insert(UiAction::TogglePathPreview)
+                .insert(PreviousComponent::<Interaction>::default())
+                .with_children(|button| {
+                    button
+                        .spawn_bundle(TextBundle {
+                            style: Style {
+                                margin: UiRect::new(
+                                    Val::Px(10.),
+                                    Val::Px(10.),
+                                    Val::Px(10.),
+                                    Val::Px(10.),
+                                ),
+                                ..default()
+                            },
+                            text: Text::from_section(
+                                path_preview_label(&path_preview),
+                                TextStyle {
+                                    font: asset_holder.font.clone(),
+                                    font_size: 32.,
+                                    color: Color::BLACK,
+                                },
+                            ),
+                            focus_policy: FocusPolicy::Pass,
+                            ..default()
+                        })
+                        .insert(PathPreviewToggleText);
+                });
+
+            let ldtk_asset = ldtk_assets.get(&asset_holder.ldtk).unwrap();
+
+            for (level_num, level) in ldtk_asset.iter_levels().enumerate() {
+                // Only point out where a level leads when it isn't just the next one
+                let branches = level_branches(ldtk_asset, level_num);
+                let branch_label = if branches == [level_num + 1] {
+                    String::new()
+                } else {
+                    let targets: Vec<String> =
+                        branches.iter().map(|b| format!("#{}", b + 1)).collect();
+                    format!(" > {}", targets.join(" "))
+                };
+
+                let shard_total = level_shard_total(ldtk_asset, level_num);
+                let shard_label = if shard_total > 0 {
+                    format!(" {}/{}", collected_shards.count(&level.iid), shard_total)
+                } else {
+                    String::new()
+                };
+
                 builder
                     .spawn_bundle(ButtonBundle {
                         style: Style {
@@ -122,14 +182,32 @@ fn spawn_pause_screen(
                                 ),
                                 ..default()
                             },
-                            text: Text::from_section(
-                                format!("#{}", level_num + 1),
-                                TextStyle {
-                                    font: asset_holder.font.clone(),
-                                    font_size: 64.,
-                                    color: Color::BLACK,
-                                },
-                            ),
+                            text: Text::from_sections([
+                                TextSection::new(
+                                    format!("#{}", level_num + 1),
+                                    TextStyle {
+                                        font: asset_holder.font.clone(),
+                                        font_size: 64.,
+                                        color: Color::BLACK,
+                                    },
+                                ),
+                                TextSection::new(
+                                    branch_label,
+                                    TextStyle {
+                                        font: asset_holder.font.clone(),
+                                        font_size: 32.,
+                                        color: Color::DARK_GRAY,
+                                    },
+                                ),
+                                TextSection::new(
+                                    shard_label,
+                                    TextStyle {
+                                        font: asset_holder.font.clone(),
+                                        font_size: 32.,
+                                        color: Color::rgb(0.87, 0.44, 0.15),
+                                    },
+                                ),
+                            ]),
                             focus_policy: FocusPolicy::Pass,
                             ..default()
                         });
@@ -143,11 +221,7 @@ fn despawn_pause_menu(
     pause_screen: Query<Entity, With<PauseScreen>>,
     mut rapier_config: ResMut<RapierConfiguration>,
 ) {
-    rapier_config.timestep_mode = TimestepMode::Variable {
-        max_dt: 1.0 / 60.0,
-        time_scale: 1.0,
-        substeps: 1,
-    };
+    rapier_config.physics_pipeline_active = true;
     commands.entity(pause_screen.single()).despawn_recursive();
 }
 
@@ -0,0 +1,97 @@
+//! Walls that are only solid during certain seconds of a level.
+//!
+//! Whether they're solid only depends on [TimeSinceLevelStart], so rewinding and fast-forwarding
+//! open and close them too.
+use crate::{
+    fixed_timestep::PhysicsAppExt, history::TimeSinceLevelStart, ldtk_fields::field_value,
+    player::ColliderBundle,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+pub struct PhaseWallPlugin;
+
+impl Plugin for PhaseWallPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<PhaseWallBundle>("PhaseWall")
+            .add_physics_system(phase_walls.after("advance_time").before("ground_detection"))
+            .add_system(show_phase_walls);
+    }
+}
+
+/// Opacity of a phase wall while it can be passed through.
+const OPEN_ALPHA: f32 = 0.25;
+
+/// Start and end times, since the level started, during which a wall is solid.
+///
+/// A start without an end stays solid until the level is over.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub struct PhaseWall {
+    windows: Vec<(f32, f32)>,
+}
+
+impl PhaseWall {
+    pub fn is_solid(&self, timestamp: f32) -> bool {
+        self.windows
+            .iter()
+            .any(|(start, end)| (*start..*end).contains(&timestamp))
+    }
+}
+
+impl From<EntityInstance> for PhaseWall {
+    fn from(entity_instance: EntityInstance) -> Self {
+        let seconds: Vec<f32> = match field_value(&entity_instance, "Solid") {
+            Some(FieldValue::Floats(values)) => values.iter().flatten().copied().collect(),
+            _ => Vec::new(),
+        };
+
+        PhaseWall {
+            windows: seconds
+                .chunks(2)
+                .map(|window| (window[0], window.get(1).copied().unwrap_or(f32::INFINITY)))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct PhaseWallBundle {
+    #[sprite_sheet_bundle]
+    #[bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    #[from_entity_instance]
+    pub phase_wall: PhaseWall,
+    #[from_entity_instance]
+    #[bundle]
+    pub collider_bundle: ColliderBundle,
+}
+
+/// Turns phase walls into sensors while they're open, so nothing collides with them.
+fn phase_walls(
+    mut commands: Commands,
+    wall_query: Query<(Entity, &PhaseWall, Option<&Sensor>)>,
+    time_since_level_start: Res<TimeSinceLevelStart>,
+) {
+    for (entity, phase_wall, sensor) in wall_query.iter() {
+        let solid = phase_wall.is_solid(time_since_level_start.0);
+
+        if solid && sensor.is_some() {
+            commands.entity(entity).remove::<Sensor>();
+        } else if !solid && sensor.is_none() {
+            commands.entity(entity).insert(Sensor);
+        }
+    }
+}
+
+fn show_phase_walls(
+    mut wall_query: Query<(&mut TextureAtlasSprite, Option<&Sensor>), With<PhaseWall>>,
+) {
+    for (mut sprite, sensor) in wall_query.iter_mut() {
+        let alpha = if sensor.is_some() { OPEN_ALPHA } else { 1. };
+
+        if sprite.color.a() != alpha {
+            sprite.color.set_a(alpha);
+        }
+    }
+}
@@ -1,9 +1,13 @@
 use crate::{
-    history::{History, Moment, TimeScale, TimeSinceLevelStart},
+    fixed_timestep::{InterpolatedTransform, PhysicsAppExt, PHYSICS_DELTA},
+    history::{History, Moment, RewindTo, TimeScale, TimeSinceLevelStart},
+    ldtk_fields::{entity_center, field_value, EntityIid},
+    player::{GroundDetection, Player},
+    switch::{activation, Activated, ActivationTargets},
     GameState,
 };
-use bevy::prelude::*;
-use bevy_ecs_ldtk::{prelude::*, utils::ldtk_grid_coords_to_translation_centered};
+use bevy::{prelude::*, sprite::Anchor};
+use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
 use iyes_loopless::prelude::*;
 
@@ -12,43 +16,221 @@ pub struct PlatformPlugin;
 impl Plugin for PlatformPlugin {
     fn build(&self, app: &mut App) {
         app.register_ldtk_entity::<PlatformBundle>("Platform")
-            //.add_system(
-            //|query: Query<
-            //(&History<PlatformMoment>, &Path),
-            //Changed<History<PlatformMoment>>,
-            //>| {
-            //query.for_each(|(x, path)| {
-            //dbg!(x.moments.len(), path.index);
-            //});
-            //},
-            //)
-            .add_system(
+            .add_physics_system(
                 platform_movement
                     .run_in_state(GameState::Gameplay)
-                    .after("update_time"),
+                    .label("platform_movement")
+                    .after("restore_interpolated_transforms")
+                    .after("advance_time"),
+            )
+            .add_physics_system(
+                crumble_platforms
+                    .run_in_state(GameState::Gameplay)
+                    .after("ground_detection")
+                    .before("platform_movement"),
+            )
+            .add_system(shake_crumbling_platforms.run_in_state(GameState::Gameplay))
+            .add_system(tile_resized_platforms)
+            .add_system(
+                rewind_platforms_to
+                    .run_in_state(GameState::Gameplay)
+                    .run_on_event::<RewindTo>(),
             );
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Debug)]
-enum PlatformMoment {
-    ChangeDirection { velocity: Vec2, position: Vec3 },
+/// What a platform does once it reaches the last point of its path.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum PathMode {
+    /// Heads straight back to the first point.
+    #[default]
+    Loop,
+    /// Retraces the path backwards, then forwards again.
+    PingPong,
+    /// Stays at the last point.
+    OneShot,
+}
+
+impl PathMode {
+    fn from_identifier(identifier: &str) -> Self {
+        match identifier {
+            "PingPong" => PathMode::PingPong,
+            "OneShot" => PathMode::OneShot,
+            _ => PathMode::Loop,
+        }
+    }
+}
+
+/// How a platform speeds up and slows down along a segment of its path.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
 }
 
-impl Default for PlatformMoment {
-    fn default() -> Self {
-        PlatformMoment::ChangeDirection {
-            velocity: Vec2::default(),
-            position: Vec3::default(),
+impl Easing {
+    fn from_identifier(identifier: &str) -> Self {
+        match identifier {
+            "EaseIn" => Easing::EaseIn,
+            "EaseOut" => Easing::EaseOut,
+            "EaseInOut" => Easing::EaseInOut,
+            _ => Easing::Linear,
         }
     }
+
+    /// Maps the fraction of time spent on a segment to the fraction of distance covered.
+    fn ease(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2. - t),
+            Easing::EaseInOut => t * t * (3. - 2. * t),
+        }
+    }
+}
+
+/// A stretch of a platform's path, waiting at a point if `from` and `to` are the same.
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct Leg {
+    from: usize,
+    to: usize,
+    easing: Easing,
+    /// Seconds into the path that the leg starts.
+    start: f32,
+    duration: f32,
 }
 
 #[derive(Clone, PartialEq, Debug, Default, Component)]
 pub struct Path {
     points: Vec<Vec3>,
-    speed: f32,
-    index: usize,
+    legs: Vec<Leg>,
+    /// Seconds before the path repeats itself, if it does.
+    period: Option<f32>,
+}
+
+impl Path {
+    /// Lays out the legs of a path.
+    ///
+    /// `easings` are for the segment that starts at each point,
+    /// with ping-ponging platforms using the same easing in both directions.
+    /// `waits` are the seconds to wait on arriving at each point.
+    fn new(
+        points: Vec<Vec3>,
+        speed: f32,
+        mode: PathMode,
+        easings: &[Easing],
+        waits: &[f32],
+    ) -> Self {
+        let len = points.len();
+
+        if len < 2 || speed <= 0. {
+            return Path {
+                points,
+                legs: Vec::new(),
+                period: None,
+            };
+        }
+
+        // The order points are visited in, with repeating paths heading back to the first one
+        let mut stops: Vec<usize> = (0..len).collect();
+        match mode {
+            PathMode::Loop => stops.push(0),
+            PathMode::PingPong => stops.extend((0..len - 1).rev()),
+            PathMode::OneShot => (),
+        }
+
+        let mut legs = Vec::new();
+        let mut start = 0.;
+
+        for pair in stops.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+
+            let wait = waits.get(from).copied().unwrap_or(0.);
+            legs.push(Leg {
+                from,
+                to: from,
+                easing: Easing::Linear,
+                start,
+                duration: wait,
+            });
+            start += wait;
+
+            let segment = if to < from && mode == PathMode::PingPong {
+                to
+            } else {
+                from
+            };
+            let duration = points[from].distance(points[to]) / speed;
+            legs.push(Leg {
+                from,
+                to,
+                easing: easings.get(segment).copied().unwrap_or_default(),
+                start,
+                duration,
+            });
+            start += duration;
+        }
+
+        let period = (mode != PathMode::OneShot && start > 0.).then_some(start);
+
+        Path {
+            points,
+            legs,
+            period,
+        }
+    }
+
+    /// Every stretch of the path the platform travels along, each only once.
+    pub fn segments(&self) -> Vec<(Vec3, Vec3)> {
+        let mut segments: Vec<(usize, usize)> = Vec::new();
+
+        for leg in self.legs.iter().filter(|leg| leg.from != leg.to) {
+            let segment = (leg.from.min(leg.to), leg.from.max(leg.to));
+
+            if !segments.contains(&segment) {
+                segments.push(segment);
+            }
+        }
+
+        segments
+            .into_iter()
+            .map(|(a, b)| (self.points[a], self.points[b]))
+            .collect()
+    }
+
+    pub fn points(&self) -> &[Vec3] {
+        &self.points
+    }
+
+    /// Where the platform is `timestamp` seconds after the level starts.
+    pub fn position(&self, timestamp: f32) -> Vec3 {
+        let timestamp = match self.period {
+            Some(period) => timestamp.rem_euclid(period),
+            None => timestamp,
+        };
+
+        match self
+            .legs
+            .iter()
+            .find(|leg| timestamp < leg.start + leg.duration)
+        {
+            Some(leg) => {
+                let progress = ((timestamp - leg.start) / leg.duration).clamp(0., 1.);
+
+                self.points[leg.from].lerp(self.points[leg.to], leg.easing.ease(progress))
+            }
+            // One-shot paths stay at the end
+            None => self
+                .legs
+                .last()
+                .map(|leg| self.points[leg.to])
+                .or_else(|| self.points.first().copied())
+                .unwrap_or_default(),
+        }
+    }
 }
 
 impl LdtkEntity for Path {
@@ -60,44 +242,50 @@ impl LdtkEntity for Path {
         _: &AssetServer,
         _: &mut Assets<TextureAtlas>,
     ) -> Self {
-        if let FieldValue::Float(Some(speed)) = entity_instance
-            .field_instances
-            .iter()
-            .find(|f| f.identifier == "Speed")
-            .and_then(|f| Some(&f.value))
-            .expect("platform should have a speed value")
+        let field = |identifier| field_value(entity_instance, identifier);
+
+        if let FieldValue::Float(Some(speed)) =
+            field("Speed").expect("platform should have a speed value")
         {
-            if let Some(FieldValue::Points(path_field)) = entity_instance
-                .field_instances
-                .iter()
-                .find(|f| f.identifier == "Path")
-                .and_then(|f| Some(&f.value))
-            {
-                let mut points = vec![ldtk_grid_coords_to_translation_centered(
-                    entity_instance.grid,
-                    layer_instance.c_hei,
-                    IVec2::splat(layer_instance.grid_size),
-                )
-                .extend(0.)];
+            if let Some(FieldValue::Points(path_field)) = field("Path") {
+                // Path points are cells for the platform's pivot to visit,
+                // but the platform's translation is its center
+                let center = entity_center(entity_instance, layer_instance);
+
+                let mut points = vec![center];
 
                 for point in path_field.iter() {
                     let point = point.expect("path points shouldn't be null");
+                    let offset = (point - entity_instance.grid) * IVec2::new(1, -1);
 
-                    points.push(
-                        ldtk_grid_coords_to_translation_centered(
-                            point,
-                            layer_instance.c_hei,
-                            IVec2::splat(layer_instance.grid_size),
-                        )
-                        .extend(0.),
-                    );
+                    points.push(center + (offset * layer_instance.grid_size).as_vec2().extend(0.));
                 }
 
-                Path {
-                    points,
-                    speed: *speed,
-                    index: 0,
-                }
+                let mode = match field("Mode") {
+                    Some(FieldValue::Enum(Some(mode))) => PathMode::from_identifier(mode),
+                    _ => PathMode::default(),
+                };
+
+                let easings: Vec<Easing> = match field("Easing") {
+                    Some(FieldValue::Enums(easings)) => easings
+                        .iter()
+                        .map(|e| {
+                            e.as_deref()
+                                .map(Easing::from_identifier)
+                                .unwrap_or_default()
+                        })
+                        .collect(),
+                    _ => Vec::new(),
+                };
+
+                let waits: Vec<f32> = match field("Wait") {
+                    Some(FieldValue::Floats(waits)) => {
+                        waits.iter().map(|w| w.unwrap_or(0.).max(0.)).collect()
+                    }
+                    _ => Vec::new(),
+                };
+
+                Path::new(points, *speed, mode, &easings, &waits)
             } else {
                 Path::default()
             }
@@ -117,98 +305,361 @@ struct PlatformBundle {
     #[from_entity_instance]
     #[bundle]
     pub collider_bundle: crate::player::ColliderBundle,
-    pub history: History<PlatformMoment>,
+    #[from_entity_instance]
+    pub iid: EntityIid,
+    pub clock: PathClock,
+    #[from_entity_instance]
+    pub crumbling: Crumbling,
+}
+
+/// The platform was activated or deactivated, having moved along its path for `path_time` seconds.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+struct ClockMoment {
+    active: bool,
+    path_time: f32,
 }
 
+/// Tracks how far along its [Path] a platform linked to a switch or pressure plate is.
+///
+/// Such platforms only move while activated, so their position depends on when that happened.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub struct PathClock {
+    history: History<ClockMoment>,
+}
+
+impl PathClock {
+    /// Drops the activations recorded after `timestamp`, so the platform retraces its path.
+    fn rewind(&mut self, timestamp: f32) {
+        self.history.rewind_to(timestamp);
+    }
+
+    fn set_active(&mut self, active: bool, timestamp: f32) {
+        let was_active = self
+            .history
+            .moments
+            .last()
+            .map(|m| m.data.active)
+            .unwrap_or(false);
+
+        if active != was_active {
+            let path_time = self.path_time(timestamp);
+
+            self.history.moments.push(Moment {
+                timestamp,
+                data: ClockMoment { active, path_time },
+            });
+        }
+    }
+
+    /// Seconds spent moving along the path by `timestamp`.
+    fn path_time(&self, timestamp: f32) -> f32 {
+        match self.history.moments.last() {
+            Some(Moment {
+                timestamp: since,
+                data:
+                    ClockMoment {
+                        active: true,
+                        path_time,
+                    },
+            }) => path_time + (timestamp - since).max(0.),
+            Some(moment) => moment.data.path_time,
+            None => 0.,
+        }
+    }
+}
+
+/// One of the tiles a resized platform is drawn with.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct PlatformTile;
+
+/// How fast crumbled platforms speed up as they fall, in pixels per second squared.
+const CRUMBLE_FALL_ACCELERATION: f32 = 900.;
+
+/// How far crumbling platforms shake either way, as a fraction of their width.
+const CRUMBLE_SHAKE: f32 = 0.05;
+
+/// How far below the bottom of the level crumbled platforms stop falling, in pixels.
+const CRUMBLE_REST_DEPTH: f32 = 64.;
+
+/// Makes a platform shake once the player lands on it, then fall after a delay.
+///
+/// Platforms without a delay never crumble.
+#[derive(Clone, PartialEq, Debug, Default, Component)]
+pub struct Crumbling {
+    delay: Option<f32>,
+    /// When the player landed on the platform, starting it shaking.
+    landed_at: Option<f32>,
+}
+
+impl From<EntityInstance> for Crumbling {
+    fn from(entity_instance: EntityInstance) -> Self {
+        let delay = match field_value(&entity_instance, "Crumble") {
+            Some(FieldValue::Float(Some(delay))) => Some(delay.max(0.)),
+            _ => None,
+        };
+
+        Crumbling {
+            delay,
+            landed_at: None,
+        }
+    }
+}
+
+impl Crumbling {
+    /// Puts the platform back in place if `timestamp` is before it was landed on.
+    fn rewind(&mut self, timestamp: f32) {
+        if self
+            .landed_at
+            .map(|landed_at| landed_at > timestamp)
+            .unwrap_or(false)
+        {
+            self.landed_at = None;
+        }
+    }
+
+    fn land(&mut self, timestamp: f32) {
+        if self.delay.is_some() && self.landed_at.is_none() {
+            self.landed_at = Some(timestamp);
+        }
+    }
+
+    /// When the platform starts falling, if it's been landed on.
+    fn fall_start(&self) -> Option<f32> {
+        Some(self.landed_at? + self.delay?)
+    }
+
+    fn is_shaking(&self, timestamp: f32) -> bool {
+        match (self.landed_at, self.fall_start()) {
+            (Some(landed_at), Some(fall_start)) => landed_at <= timestamp && timestamp < fall_start,
+            _ => false,
+        }
+    }
+
+    /// How far the platform has fallen by `timestamp`, stopping once it's fallen `max_fall`.
+    fn fall_offset(&self, timestamp: f32, max_fall: f32) -> Vec3 {
+        let fall_time = self
+            .fall_start()
+            .map(|fall_start| (timestamp - fall_start).max(0.))
+            .unwrap_or(0.);
+
+        Vec3::NEG_Y * (0.5 * CRUMBLE_FALL_ACCELERATION * fall_time * fall_time).min(max_fall)
+    }
+}
+
+fn crumble_platforms(
+    player_query: Query<&GroundDetection, With<Player>>,
+    mut platform_query: Query<(Entity, &mut Crumbling)>,
+    time_scale: Res<TimeScale>,
+    time_since_level_start: Res<TimeSinceLevelStart>,
+) {
+    for (entity, mut crumbling) in platform_query.iter_mut() {
+        crumbling.rewind(time_since_level_start.0);
+
+        let stood_on = player_query
+            .iter()
+            .any(|ground| ground.on_ground && ground.ground_entity == Some(entity));
+
+        if stood_on && time_scale.0 > 0. {
+            crumbling.land(time_since_level_start.0);
+        }
+    }
+}
+
+fn shake_crumbling_platforms(
+    platform_query: Query<(Entity, &Crumbling, Option<&Children>)>,
+    mut sprite_query: Query<&mut TextureAtlasSprite>,
+    time_since_level_start: Res<TimeSinceLevelStart>,
+) {
+    let now = time_since_level_start.0;
+
+    for (entity, crumbling, children) in platform_query.iter() {
+        if crumbling.delay.is_none() {
+            continue;
+        }
+
+        let shaking = crumbling.is_shaking(now);
+
+        // Resized platforms are drawn by their tiles
+        let tiles = children.into_iter().flat_map(|c| c.iter().copied());
+
+        for sprite_entity in std::iter::once(entity).chain(tiles) {
+            if let Ok(mut sprite) = sprite_query.get_mut(sprite_entity) {
+                sprite.color = Color::rgb(0.85, 0.63, 0.4);
+                sprite.anchor = if shaking {
+                    Anchor::Custom(Vec2::new((now * 60.).sin() * CRUMBLE_SHAKE, 0.))
+                } else {
+                    Anchor::Center
+                };
+            }
+        }
+    }
+}
+
+type NewPlatforms<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static EntityInstance,
+        &'static mut Transform,
+        &'static mut Collider,
+        &'static TextureAtlasSprite,
+        &'static Handle<TextureAtlas>,
+    ),
+    Added<Path>,
+>;
+
+/// Undoes the scale bevy_ecs_ldtk gives resized platforms, resizing their colliders instead, and
+/// covers them in tiles rather than stretching their sprite.
+fn tile_resized_platforms(mut commands: Commands, mut platform_query: NewPlatforms) {
+    for (entity, entity_instance, mut transform, mut collider, sprite, texture_atlas) in
+        platform_query.iter_mut()
+    {
+        transform.scale = Vec3::ONE;
+
+        let size = Vec2::new(entity_instance.width as f32, entity_instance.height as f32);
+        let tile_size = entity_instance
+            .tile
+            .as_ref()
+            .map(|tile| Vec2::new(tile.w as f32, tile.h as f32))
+            .unwrap_or(size);
+
+        if size == tile_size {
+            continue;
+        }
+
+        *collider = Collider::cuboid(size.x / 2., size.y / 2.);
+
+        // Only the platform's own sprite is removed, since hiding it would hide its tiles too
+        commands.entity(entity).remove::<TextureAtlasSprite>();
+
+        let columns = (size.x / tile_size.x).ceil() as usize;
+        let rows = (size.y / tile_size.y).ceil() as usize;
+
+        commands.entity(entity).with_children(|platform| {
+            for row in 0..rows {
+                for column in 0..columns {
+                    let corner = Vec2::new(column as f32, row as f32) * tile_size;
+                    // Tiles along the far edges get squashed to fit
+                    let cell_size = (size - corner).min(tile_size);
+
+                    let center = Vec2::new(
+                        -size.x / 2. + corner.x + cell_size.x / 2.,
+                        size.y / 2. - corner.y - cell_size.y / 2.,
+                    );
+
+                    platform
+                        .spawn_bundle(SpriteSheetBundle {
+                            sprite: TextureAtlasSprite {
+                                index: sprite.index,
+                                custom_size: Some(cell_size),
+                                ..default()
+                            },
+                            texture_atlas: texture_atlas.clone(),
+                            transform: Transform::from_translation(center.extend(0.)),
+                            ..default()
+                        })
+                        .insert(PlatformTile);
+                }
+            }
+        });
+    }
+}
+
+/// Where a platform is at `timestamp`, or will be if whatever activates it stays as it is.
+///
+/// `linked` is whether a switch or pressure plate activates the platform.
+pub fn platform_position(
+    path: &Path,
+    clock: &PathClock,
+    crumbling: &Crumbling,
+    linked: bool,
+    timestamp: f32,
+) -> Vec3 {
+    let path_time = if linked {
+        clock.path_time(timestamp)
+    } else {
+        timestamp
+    };
+
+    let position = path.position(path_time);
+
+    // Crumbled platforms come to rest out of sight below the level, instead of falling forever
+    position + crumbling.fall_offset(timestamp, (position.y + CRUMBLE_REST_DEPTH).max(0.))
+}
+
+/// Steers platforms to where their path puts them by the end of the step.
+///
+/// Their position only depends on the time, when they were activated if they're linked to a
+/// switch, and when they were landed on if they crumble, so rewinding and fast-forwarding are
+/// exact.
 fn platform_movement(
     mut query: Query<(
-        &mut Transform,
-        &mut Path,
-        &mut History<PlatformMoment>,
+        &Transform,
+        &Path,
+        &EntityIid,
+        &mut PathClock,
+        &Crumbling,
         &mut Velocity,
     )>,
+    activators: Query<(&ActivationTargets, &Activated)>,
     time_scale: Res<TimeScale>,
     time_since_level_start: Res<TimeSinceLevelStart>,
 ) {
-    if time_scale.0 > 0. {
-        for (transform, mut path, mut history, mut velocity) in query.iter_mut() {
-            let goal_position = path.points[path.index];
-            let mut new_velocity = (goal_position - transform.translation)
-                .truncate()
-                .normalize()
-                * path.speed;
-
-            let goal_passed = match history.moments.last() {
-                Some(Moment {
-                    data:
-                        PlatformMoment::ChangeDirection {
-                            velocity: goal_velocity,
-                            ..
-                        },
-                    ..
-                }) => {
-                    if new_velocity.dot(*goal_velocity) < 0. {
-                        true
-                    } else {
-                        false
-                    }
-                }
-                None => true,
-            };
+    let now = time_since_level_start.0;
 
-            if goal_passed {
-                // passed it!
-                path.index = (path.index + 1) % path.points.len();
-                let goal_position = path.points[path.index];
+    for (transform, path, iid, mut clock, crumbling, mut velocity) in query.iter_mut() {
+        let active = activation(&iid.0, activators.iter());
 
-                new_velocity = (goal_position - transform.translation)
-                    .normalize_or_zero()
-                    .truncate()
-                    * path.speed;
+        if let Some(active) = active {
+            clock.rewind(now);
 
-                history.moments.push(Moment {
-                    data: PlatformMoment::ChangeDirection {
-                        velocity: new_velocity,
-                        position: transform.translation,
-                    },
-                    timestamp: time_since_level_start.0,
-                });
+            // Activations only get recorded going forwards, and replayed going backwards
+            if time_scale.0 > 0. {
+                clock.set_active(active, now);
             }
-
-            velocity.linvel = new_velocity * time_scale.0;
         }
-    } else {
-        for (mut transform, mut path, mut history, mut velocity) in query.iter_mut() {
-            // Popping items off the history if we've passed them
-            if let Some(goal_moment) = history.moments.last() {
-                if time_since_level_start.0 < goal_moment.timestamp
-                    || time_since_level_start.0 == 0.
-                {
-                    let PlatformMoment::ChangeDirection { position, .. } = goal_moment.data;
-                    if history.moments.len() > 1 {
-                        transform.translation = position;
-
-                        history.moments.pop();
-
-                        if path.index == 0 {
-                            path.index = path.points.len() - 1;
-                        } else {
-                            path.index -= 1;
-                        }
-                    }
-                }
-            }
 
-            // actually apply the history
-            if let Some(goal_moment) = history.moments.last() {
-                match goal_moment.data {
-                    PlatformMoment::ChangeDirection {
-                        position: _,
-                        velocity: goal_velocity,
-                    } => {
-                        velocity.linvel = goal_velocity * time_scale.0;
-                    }
-                }
+        let target = platform_position(path, &clock, crumbling, active.is_some(), now);
+
+        velocity.linvel = (target - transform.translation).truncate() / PHYSICS_DELTA;
+    }
+}
+
+type RewindingPlatforms<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut Transform,
+        Option<&'static mut InterpolatedTransform>,
+        &'static Path,
+        &'static EntityIid,
+        &'static mut PathClock,
+        &'static mut Crumbling,
+        &'static mut Velocity,
+    ),
+>;
+
+/// Jumps platforms straight to where they were at the [RewindTo] timestamp.
+fn rewind_platforms_to(
+    mut rewind_to_events: EventReader<RewindTo>,
+    mut query: RewindingPlatforms,
+    activators: Query<(&ActivationTargets, &Activated)>,
+) {
+    for RewindTo(timestamp) in rewind_to_events.iter() {
+        for (mut transform, interpolated, path, iid, mut clock, mut crumbling, mut velocity) in
+            query.iter_mut()
+        {
+            let linked = activation(&iid.0, activators.iter()).is_some();
+
+            clock.rewind(*timestamp);
+            crumbling.rewind(*timestamp);
+
+            transform.translation = platform_position(path, &clock, &crumbling, linked, *timestamp);
+            velocity.linvel = Vec2::ZERO;
+
+            if let Some(mut interpolated) = interpolated {
+                interpolated.teleport(transform.translation);
             }
         }
     }
@@ -1,9 +1,12 @@
-use crate::{animation::*, from_component::FromComponentPlugin, GameState};
-use bevy::prelude::*;
+use crate::{
+    animation::*, character_controller::CharacterController, dash::Dash,
+    fixed_timestep::PhysicsAppExt, from_component::FromComponentPlugin, history::TimeScale,
+    wall::OneWay, GameState,
+};
+use bevy::{input::InputSystem, prelude::*};
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
-use iyes_loopless::prelude::*;
-use std::collections::HashSet;
+use iyes_loopless::{fixedtimestep::FixedTimestepInfo, prelude::*};
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
 pub struct Player;
@@ -15,7 +18,19 @@ pub struct PlayerPlugin;
 pub enum Vitality {
     #[default]
     Alive,
-    Dead,
+    Dead(DeathCause),
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum DeathCause {
+    /// Fell out of the level.
+    Fell,
+    /// Didn't escape before Apophis hit the earth.
+    Impact,
+    /// Touched a [crate::hazard::Hazard].
+    Hazard,
+    /// Squeezed between solid objects.
+    Crushed,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash, Component, Default)]
@@ -24,6 +39,9 @@ pub enum PlayerAnimationState {
     Idle,
     Running,
     Falling,
+    WallSliding,
+    WallJumping,
+    Dashing,
     Dead,
 }
 
@@ -33,6 +51,9 @@ impl From<PlayerAnimationState> for SpriteSheetAnimation {
             PlayerAnimationState::Idle => 0..1,
             PlayerAnimationState::Running => 4..8,
             PlayerAnimationState::Falling => 8..9,
+            PlayerAnimationState::WallSliding => 1..3,
+            PlayerAnimationState::WallJumping => 9..11,
+            PlayerAnimationState::Dashing => 13..15,
             PlayerAnimationState::Dead => 12..13,
         };
 
@@ -50,23 +71,39 @@ impl From<PlayerAnimationState> for SpriteSheetAnimation {
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(movement.run_in_state(GameState::Gameplay).label("movement"))
-            .add_system(spawn_ground_sensor.run_in_state(GameState::Gameplay))
-            .add_system(
+        app.init_resource::<PlayerInput>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                read_player_input
+                    .run_in_state(GameState::Gameplay)
+                    .after(InputSystem),
+            )
+            .add_physics_system(movement.run_in_state(GameState::Gameplay).label("movement"))
+            .add_physics_system(
                 ground_detection
                     .run_in_state(GameState::Gameplay)
+                    .label("ground_detection")
+                    .after("restore_interpolated_transforms")
+                    .before("movement"),
+            )
+            .add_physics_system(
+                wall_detection
+                    .run_in_state(GameState::Gameplay)
+                    .after("restore_interpolated_transforms")
                     .before("movement"),
             )
             .add_plugin(FromComponentPlugin::<
                 PlayerAnimationState,
                 SpriteSheetAnimation,
             >::new())
-            .add_system(
+            .add_physics_system(
                 move_object_with_ground
                     .run_in_state(GameState::Gameplay)
-                    .after("movement"),
+                    .after("ground_detection")
+                    .before("movement"),
             )
             .add_system(kill_out_of_bounds_player.run_in_state(GameState::Gameplay))
+            .add_system(kill_crushed_player.run_in_state(GameState::Gameplay))
             //.add_system(
             //|mut collision_events: EventReader<CollisionEvent>,
             //mut contact_force_events: EventReader<ContactForceEvent>| {
@@ -78,7 +115,7 @@ impl Plugin for PlayerPlugin {
             //}
             //},
             //)
-            //.add_system(|query: Query<&GroundSensor, Changed<GroundSensor>>| {
+            //.add_system(|query: Query<&GroundDetection, Changed<GroundDetection>>| {
             //query.for_each(|gs| {
             //dbg!(gs);
             //});
@@ -87,15 +124,67 @@ impl Plugin for PlayerPlugin {
     }
 }
 
-#[derive(Component, Clone, Eq, PartialEq, Debug)]
-pub struct GroundSensor {
-    pub ground_detection_entity: Entity,
-    pub intersecting_ground_entities: HashSet<Entity>,
+/// Player input, collected every frame for use in the fixed timestep.
+///
+/// Presses are held onto until a physics step consumes them,
+/// so they aren't missed or repeated when steps and frames don't line up.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct PlayerInput {
+    pub left: bool,
+    pub right: bool,
+    pub down: bool,
+    pub jump_held: bool,
+    pub jump_pressed: bool,
+    pub dash_pressed: bool,
 }
 
-#[derive(Clone, Default, Component)]
+/// Detects the ground below an entity by shape-casting its feet downward every frame.
+#[derive(Clone, Debug, Default, Component)]
 pub struct GroundDetection {
     pub on_ground: bool,
+    /// The entity being stood on, if any.
+    pub ground_entity: Option<Entity>,
+    /// Surface normal of the ground, pointing away from it.
+    pub normal: Vec2,
+    /// Distance between the entity's feet and the ground.
+    pub distance: f32,
+    /// Velocity of the ground that this entity is being carried along at.
+    pub ground_velocity: Vec2,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum WallSide {
+    Left,
+    Right,
+}
+
+impl WallSide {
+    /// The x direction pointing from the detector into this wall.
+    pub fn direction(&self) -> f32 {
+        match self {
+            WallSide::Left => -1.,
+            WallSide::Right => 1.,
+        }
+    }
+}
+
+/// Detects walls on either side of an entity by shape-casting sideways every frame.
+#[derive(Clone, Debug, Default, Component)]
+pub struct WallDetection {
+    pub on_left_wall: bool,
+    pub on_right_wall: bool,
+}
+
+impl WallDetection {
+    /// The side of the wall being touched, preferring the one the detector is pushing into.
+    pub fn wall_side(&self, push_direction: f32) -> Option<WallSide> {
+        match (self.on_left_wall, self.on_right_wall) {
+            (true, true) if push_direction > 0. => Some(WallSide::Right),
+            (true, _) => Some(WallSide::Left),
+            (false, true) => Some(WallSide::Right),
+            (false, false) => None,
+        }
+    }
 }
 
 #[derive(Clone, Default, Bundle, LdtkEntity)]
@@ -108,6 +197,9 @@ pub struct PlayerBundle {
     pub collider_bundle: ColliderBundle,
     pub player: Player,
     pub ground_detection: GroundDetection,
+    pub wall_detection: WallDetection,
+    pub character_controller: CharacterController,
+    pub dash: Dash,
     pub animation: PlayerAnimationState,
     pub vitality: Vitality,
 }
@@ -121,33 +213,92 @@ pub struct ColliderBundle {
     pub friction: Friction,
     pub restitution: Restitution,
     pub active_events: ActiveEvents,
+    pub active_collision_types: ActiveCollisionTypes,
 }
 
 impl From<EntityInstance> for ColliderBundle {
     fn from(entity_instance: EntityInstance) -> ColliderBundle {
+        // bevy_ecs_ldtk puts entities at their center, whatever their pivot, and scales them by
+        // their size over their tile's, so colliders are sized in tile units to match them
+        let half_extents = entity_instance
+            .tile
+            .as_ref()
+            .map(|tile| Vec2::new(tile.w as f32, tile.h as f32))
+            .unwrap_or_else(|| {
+                Vec2::new(entity_instance.width as f32, entity_instance.height as f32)
+            })
+            / 2.;
+
         match entity_instance.identifier.as_ref() {
             "Player" => ColliderBundle {
                 collider: Collider::cuboid(10., 24.),
-                rigid_body: RigidBody::Dynamic,
+                rigid_body: RigidBody::KinematicPositionBased,
                 locked_axes: LockedAxes::ROTATION_LOCKED,
-                friction: Friction {
-                    coefficient: 0.1,
-                    combine_rule: CoefficientCombineRule::Multiply,
-                },
-                restitution: Restitution {
-                    coefficient: 0.,
-                    combine_rule: CoefficientCombineRule::Multiply,
-                },
+                // Kinematic bodies don't collide with static ones by default,
+                // but goals and hazards still need to detect the player
+                active_collision_types: ActiveCollisionTypes::default()
+                    | ActiveCollisionTypes::KINEMATIC_STATIC
+                    | ActiveCollisionTypes::KINEMATIC_KINEMATIC,
+                ..Default::default()
+            },
+            // Rotating platforms and pendulums turn, so they aren't rotation locked
+            "RotatingPlatform" => ColliderBundle {
+                collider: Collider::cuboid(half_extents.x, half_extents.y),
+                rigid_body: RigidBody::KinematicVelocityBased,
+                ..Default::default()
+            },
+            "Pendulum" => ColliderBundle {
+                collider: Collider::ball(half_extents.min_element()),
+                rigid_body: RigidBody::KinematicVelocityBased,
+                active_events: ActiveEvents::COLLISION_EVENTS,
                 ..Default::default()
             },
             "Platform" => ColliderBundle {
-                collider: Collider::cuboid(16., 16.),
+                collider: Collider::cuboid(half_extents.x, half_extents.y),
                 rigid_body: RigidBody::KinematicVelocityBased,
                 locked_axes: LockedAxes::ROTATION_LOCKED,
                 ..Default::default()
             },
-            "Goal" => ColliderBundle {
-                collider: Collider::cuboid(16., 16.),
+            "Switch" => ColliderBundle {
+                collider: Collider::cuboid(10., 12.),
+                rigid_body: RigidBody::Fixed,
+                active_events: ActiveEvents::COLLISION_EVENTS,
+                ..Default::default()
+            },
+            // Only the raised part at the bottom of the tile can be stood on
+            "PressurePlate" => ColliderBundle {
+                collider: Collider::compound(vec![(
+                    Vec2::new(0., -12.),
+                    0.,
+                    Collider::cuboid(12., 4.),
+                )]),
+                rigid_body: RigidBody::Fixed,
+                ..Default::default()
+            },
+            "PhaseWall" => ColliderBundle {
+                collider: Collider::cuboid(half_extents.x, half_extents.y),
+                rigid_body: RigidBody::Fixed,
+                ..Default::default()
+            },
+            "Key" => ColliderBundle {
+                collider: Collider::cuboid(12., 8.),
+                rigid_body: RigidBody::Fixed,
+                active_events: ActiveEvents::COLLISION_EVENTS,
+                ..Default::default()
+            },
+            "Door" => ColliderBundle {
+                collider: Collider::cuboid(half_extents.x, half_extents.y),
+                rigid_body: RigidBody::Fixed,
+                ..Default::default()
+            },
+            "TimeShard" => ColliderBundle {
+                collider: Collider::ball(10.),
+                rigid_body: RigidBody::Fixed,
+                active_events: ActiveEvents::COLLISION_EVENTS,
+                ..Default::default()
+            },
+            "Goal" | "Checkpoint" => ColliderBundle {
+                collider: Collider::cuboid(half_extents.x, half_extents.y),
                 rigid_body: RigidBody::Fixed,
                 locked_axes: LockedAxes::ROTATION_LOCKED,
                 active_events: ActiveEvents::COLLISION_EVENTS,
@@ -158,53 +309,107 @@ impl From<EntityInstance> for ColliderBundle {
     }
 }
 
+impl From<IntGridCell> for ColliderBundle {
+    fn from(int_grid_cell: IntGridCell) -> ColliderBundle {
+        match int_grid_cell.value {
+            // Hazards are a little smaller than their tiles to be forgiving
+            2 | 3 => ColliderBundle {
+                collider: Collider::cuboid(12., 12.),
+                rigid_body: RigidBody::Fixed,
+                active_events: ActiveEvents::COLLISION_EVENTS,
+                ..Default::default()
+            },
+            _ => ColliderBundle::default(),
+        }
+    }
+}
+
+pub fn read_player_input(keys: Res<Input<KeyCode>>, mut input: ResMut<PlayerInput>) {
+    input.left = keys.pressed(KeyCode::A) || keys.pressed(KeyCode::Left);
+    input.right = keys.pressed(KeyCode::D) || keys.pressed(KeyCode::Right);
+    input.down = keys.pressed(KeyCode::S) || keys.pressed(KeyCode::Down);
+    input.jump_held = keys.pressed(KeyCode::Space);
+    input.jump_pressed |= keys.just_pressed(KeyCode::Space);
+    input.dash_pressed |= keys.just_pressed(KeyCode::LShift);
+}
+
+type MovingPlayers<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut CharacterController,
+        &'static mut PlayerAnimationState,
+        &'static mut TextureAtlasSprite,
+        &'static GroundDetection,
+        &'static WallDetection,
+        &'static Vitality,
+    ),
+    With<Player>,
+>;
+
 pub fn movement(
-    input: Res<Input<KeyCode>>,
-    mut query: Query<
-        (
-            &mut Velocity,
-            &mut PlayerAnimationState,
-            &mut TextureAtlasSprite,
-            &GroundDetection,
-            &Vitality,
-        ),
-        With<Player>,
-    >,
-    time: Res<Time>,
-    mut x_velocity_contribution: Local<f32>,
+    mut input: ResMut<PlayerInput>,
+    mut query: MovingPlayers,
+    one_ways: Query<(), With<OneWay>>,
+    frictions: Query<&Friction>,
+    timestep: Res<FixedTimestepInfo>,
 ) {
-    for (mut velocity, mut animation_state, mut sprite, ground_detection, vitality) in
-        query.iter_mut()
+    let delta = timestep.timestep().as_secs_f32();
+    let jump_pressed = std::mem::take(&mut input.jump_pressed);
+
+    for (
+        mut controller,
+        mut animation_state,
+        mut sprite,
+        ground_detection,
+        wall_detection,
+        vitality,
+    ) in query.iter_mut()
     {
-        let right = if *vitality == Vitality::Alive
-            && (input.pressed(KeyCode::D) || input.pressed(KeyCode::Right))
-        {
+        let right = if *vitality == Vitality::Alive && input.right {
             1.
         } else {
             0.
         };
-        let left = if *vitality == Vitality::Alive
-            && (input.pressed(KeyCode::A) || input.pressed(KeyCode::Left))
-        {
+        let left = if *vitality == Vitality::Alive && input.left {
             1.
         } else {
             0.
         };
 
         let max_contribution = 250.;
+
+        let wall_side = if ground_detection.on_ground {
+            None
+        } else {
+            wall_detection.wall_side(right - left)
+        };
+
+        // Only slide down walls that are being pushed into
+        let wall_sliding = match wall_side {
+            Some(side) => (right - left) * side.direction() > 0. && controller.velocity.y <= 0.,
+            None => false,
+        };
+
         if ground_detection.on_ground {
-            let goal = (right - left) * max_contribution;
-            *x_velocity_contribution +=
-                (goal - *x_velocity_contribution) / 2. * time.delta_seconds() * 70.;
+            // How well the ground can be gripped compared to an ordinary wall.
+            // Slippery ground is slow to speed up and stop on, and sticky ground slows you down.
+            let grip = ground_detection
+                .ground_entity
+                .and_then(|ground| frictions.get(ground).ok())
+                .map(|friction| friction.coefficient / Friction::default().coefficient)
+                .unwrap_or(1.);
+
+            let goal = (right - left) * max_contribution / grip.max(1.);
+            let x_velocity = controller.velocity.x
+                + (goal - controller.velocity.x) / 2. * delta * 70. * grip.min(1.);
 
             if goal.abs() > 0. {
                 if *animation_state != PlayerAnimationState::Running {
                     *animation_state = PlayerAnimationState::Running;
                 }
-            } else {
-                if *animation_state != PlayerAnimationState::Idle {
-                    *animation_state = PlayerAnimationState::Idle;
-                }
+            } else if *animation_state != PlayerAnimationState::Idle {
+                *animation_state = PlayerAnimationState::Idle;
             }
 
             if goal > 0. {
@@ -213,130 +418,233 @@ pub fn movement(
                 sprite.flip_x = true;
             }
 
-            velocity.linvel.x = *x_velocity_contribution;
+            // Run along the slope of the ground, so slopes are climbed as fast as flat ground
+            let along_ground = Vec2::new(ground_detection.normal.y, -ground_detection.normal.x);
+            controller.velocity = along_ground * x_velocity / along_ground.x;
         } else {
-            if *animation_state != PlayerAnimationState::Falling {
+            if wall_sliding {
+                if *animation_state != PlayerAnimationState::WallSliding {
+                    *animation_state = PlayerAnimationState::WallSliding;
+                }
+            } else if (*animation_state != PlayerAnimationState::WallJumping
+                || controller.velocity.y <= 0.)
+                && *animation_state != PlayerAnimationState::Falling
+            {
+                // Wall jumps keep their animation until the player starts falling
                 *animation_state = PlayerAnimationState::Falling;
             }
-            let contribution = (right - left) * 1200. * time.delta_seconds();
 
-            if contribution < 0. && velocity.linvel.x > -max_contribution {
-                velocity.linvel.x += contribution;
-            } else if contribution > 0. && velocity.linvel.x < max_contribution {
-                velocity.linvel.x += contribution;
-            }
+            let contribution = (right - left) * 1200. * delta;
 
-            *x_velocity_contribution = velocity.linvel.x;
+            if (contribution < 0. && controller.velocity.x > -max_contribution)
+                || (contribution > 0. && controller.velocity.x < max_contribution)
+            {
+                controller.velocity.x += contribution;
+            }
         }
 
-        if input.just_pressed(KeyCode::Space)
-            && (ground_detection.on_ground)
-            && *vitality == Vitality::Alive
-        {
-            velocity.linvel.y = velocity.linvel.y.max(0.) + 400.;
-        } else if input.pressed(KeyCode::Space) && *vitality == Vitality::Alive {
-            velocity.linvel.y -= 900. * time.delta_seconds();
+        let wall_jump_side = wall_side.filter(|_| jump_pressed && *vitality == Vitality::Alive);
+
+        let on_one_way = ground_detection
+            .ground_entity
+            .map(|ground| one_ways.contains(ground))
+            .unwrap_or(false);
+
+        if jump_pressed && (ground_detection.on_ground) && *vitality == Vitality::Alive {
+            if input.down && on_one_way {
+                // Down + jump drops through the one-way wall instead
+                controller.drop_through = 0.2;
+            } else {
+                controller.velocity.y = controller.velocity.y.max(0.) + 400.;
+            }
+        } else if let Some(side) = wall_jump_side {
+            // Kick off of the wall, away from it
+            let away = -side.direction();
+
+            controller.velocity = Vec2::new(away * max_contribution, 400.);
+
+            sprite.flip_x = away < 0.;
+            *animation_state = PlayerAnimationState::WallJumping;
+        } else if input.jump_held && *vitality == Vitality::Alive {
+            controller.velocity.y -= 900. * delta;
         } else {
-            velocity.linvel.y -= 1200. * time.delta_seconds();
+            controller.velocity.y -= 1200. * delta;
         }
 
-        if *vitality == Vitality::Dead {
+        if wall_sliding {
+            // Friction against the wall limits how fast you can slide down it
+            let max_slide_speed = 100.;
+            controller.velocity.y = controller.velocity.y.max(-max_slide_speed);
+
+            sprite.flip_x = wall_side == Some(WallSide::Right);
+        }
+
+        if let Vitality::Dead(_) = vitality {
             *animation_state = PlayerAnimationState::Dead;
         }
     }
 }
 
 pub fn move_object_with_ground(
-    mut detectors: Query<(Entity, &mut Velocity, &GroundDetection)>,
-    sensors: Query<&GroundSensor>,
-    velocities: Query<&Velocity, Without<GroundDetection>>,
+    mut detectors: Query<(
+        &GlobalTransform,
+        &mut CharacterController,
+        &mut GroundDetection,
+    )>,
+    velocities: Query<(&GlobalTransform, &Velocity), Without<GroundDetection>>,
 ) {
-    for (detector_entity, mut detect_velocity, detector) in detectors.iter_mut() {
-        if detector.on_ground {
-            if let Some(sensor) = sensors
-                .iter()
-                .find(|s| s.ground_detection_entity == detector_entity)
-            {
-                // what am I standing on?
-                for ground_entity in sensor.intersecting_ground_entities.iter() {
-                    if let Ok(velocity) = velocities.get(*ground_entity) {
-                        detect_velocity.linvel.x += velocity.linvel.x;
-                    }
-                }
+    for (global_transform, mut controller, mut detector) in detectors.iter_mut() {
+        // what am I standing on?
+        // Platform velocities are already scaled by the TimeScale,
+        // so this carries through rewinds and fast-forwards too.
+        let ground_velocity = match detector.ground_entity.map(|e| velocities.get(e)) {
+            Some(Ok((ground_transform, velocity))) if detector.on_ground => {
+                // Turning ground moves faster the further from its center it's stood on
+                let offset =
+                    (global_transform.translation() - ground_transform.translation()).truncate();
+
+                velocity.linvel + offset.perp() * velocity.angvel
             }
+            _ => Vec2::ZERO,
+        };
+
+        // Entities leaving the ground inherit the momentum of what they were standing on
+        if !detector.on_ground {
+            controller.velocity += detector.ground_velocity;
         }
+
+        detector.ground_velocity = ground_velocity;
     }
 }
 
-pub fn spawn_ground_sensor(
-    mut commands: Commands,
-    detect_ground_for: Query<(Entity, &Transform), Added<GroundDetection>>,
-) {
-    for (entity, transform) in detect_ground_for.iter() {
-        let detector_shape = Collider::cuboid(9., 2.);
-
-        let sensor_translation = Vec3::new(0., -24., 0.) / transform.scale;
-
-        commands.entity(entity).with_children(|builder| {
-            builder
-                .spawn()
-                .insert(detector_shape)
-                .insert(Sensor)
-                .insert(Transform::from_translation(sensor_translation))
-                .insert(GlobalTransform::default())
-                .insert(ActiveEvents::COLLISION_EVENTS)
-                .insert(GroundSensor {
-                    ground_detection_entity: entity,
-                    intersecting_ground_entities: HashSet::new(),
-                });
-        });
-    }
+/// Size of the collider of an entity with [GroundDetection] or [WallDetection].
+///
+/// Returns the half extents, accounting for the scale of the entity.
+fn detector_half_extents(collider: &Collider, scale: Vec3) -> Vec2 {
+    collider
+        .as_cuboid()
+        .map(|cuboid| cuboid.half_extents())
+        .unwrap_or_default()
+        * scale.truncate()
 }
 
 pub fn ground_detection(
-    mut ground_detectors: Query<&mut GroundDetection>,
-    mut ground_sensors: Query<(Entity, &mut GroundSensor)>,
-    mut collisions: EventReader<CollisionEvent>,
-    collidables: Query<Entity, (With<Collider>, Without<Sensor>)>,
+    rapier_context: Res<RapierContext>,
+    mut ground_detectors: Query<(
+        Entity,
+        &GlobalTransform,
+        &Collider,
+        &mut GroundDetection,
+        Option<&CharacterController>,
+    )>,
+    one_ways: Query<(&GlobalTransform, &Collider), With<OneWay>>,
 ) {
-    for (entity, mut ground_sensor) in ground_sensors.iter_mut() {
-        for collision in collisions.iter() {
-            match collision {
-                CollisionEvent::Started(a, b, _) => {
-                    let (sensor, other) = if *a == entity {
-                        (a, b)
-                    } else if *b == entity {
-                        (b, a)
-                    } else {
-                        continue;
-                    };
-
-                    if collidables.contains(*other) {
-                        if *sensor == entity {
-                            ground_sensor.intersecting_ground_entities.insert(*other);
-                        }
-                    }
-                }
-                CollisionEvent::Stopped(a, b, _) => {
-                    let (sensor, other) = if *a == entity {
-                        (a, b)
-                    } else if *b == entity {
-                        (b, a)
-                    } else {
-                        continue;
-                    };
-
-                    if *sensor == entity {
-                        ground_sensor.intersecting_ground_entities.remove(other);
-                    }
+    // How far below the feet still counts as standing on something
+    let reach = 2.;
+    // Surfaces steeper than this aren't ground
+    let min_normal_y = 0.6;
+
+    for (entity, global_transform, collider, mut ground_detection, controller) in
+        ground_detectors.iter_mut()
+    {
+        let (scale, _, translation) = global_transform.to_scale_rotation_translation();
+        let half_extents = detector_half_extents(collider, scale);
+
+        // One-way walls are only ground while the feet are on top of them
+        let dropping = controller.map(|c| c.drop_through > 0.).unwrap_or(false);
+        let feet_height = translation.y - half_extents.y;
+        let stands_on = |other| match one_ways.get(other) {
+            Ok((other_transform, other_collider)) => {
+                !dropping && OneWay::supports(other_transform, other_collider, feet_height)
+            }
+            Err(_) => true,
+        };
+
+        // A thin slice of the feet, slightly narrower so walls aren't mistaken for ground.
+        // It starts a little above the feet so sinking into the ground still gives good normals.
+        let lift = 4.;
+        let feet_half_height = 1.;
+        let feet = Collider::cuboid(half_extents.x - 1., feet_half_height);
+        let feet_position =
+            translation.truncate() + Vec2::new(0., lift + feet_half_height - half_extents.y);
+
+        let hit = rapier_context.cast_shape(
+            feet_position,
+            0.,
+            Vec2::NEG_Y,
+            &feet,
+            lift + reach,
+            QueryFilter::new()
+                .exclude_sensors()
+                .exclude_rigid_body(entity)
+                .predicate(&stands_on),
+        );
+
+        *ground_detection = match hit {
+            Some((ground_entity, toi)) => {
+                // Already overlapping, so there's no meaningful normal
+                let normal = if toi.toi <= 0. { Vec2::Y } else { -toi.normal1 };
+
+                GroundDetection {
+                    on_ground: normal.y >= min_normal_y,
+                    ground_entity: Some(ground_entity),
+                    normal,
+                    distance: (toi.toi - lift).max(0.),
+                    ..*ground_detection
                 }
             }
-        }
+            None => GroundDetection {
+                on_ground: false,
+                ground_entity: None,
+                normal: Vec2::ZERO,
+                distance: f32::INFINITY,
+                ..*ground_detection
+            },
+        };
+    }
+}
 
-        if let Ok(mut ground_detection) =
-            ground_detectors.get_mut(ground_sensor.ground_detection_entity)
-        {
-            ground_detection.on_ground = ground_sensor.intersecting_ground_entities.len() > 0;
+pub fn wall_detection(
+    rapier_context: Res<RapierContext>,
+    mut wall_detectors: Query<(Entity, &GlobalTransform, &Collider, &mut WallDetection)>,
+    one_ways: Query<(), With<OneWay>>,
+) {
+    // How far to the side still counts as touching a wall
+    let reach = 2.;
+    // Surfaces flatter than this aren't walls
+    let min_normal_x = 0.7;
+    // One-way walls can always be passed through sideways, so they can't be slid down or kicked off
+    let is_solid = |other| !one_ways.contains(other);
+
+    for (entity, global_transform, collider, mut wall_detection) in wall_detectors.iter_mut() {
+        let (scale, _, translation) = global_transform.to_scale_rotation_translation();
+        let half_extents = detector_half_extents(collider, scale);
+
+        // Shorter than the entity so floors and ceilings aren't mistaken for walls
+        let side = Collider::cuboid(reach, half_extents.y - 4.);
+
+        for wall_side in [WallSide::Left, WallSide::Right] {
+            let direction = Vec2::new(wall_side.direction(), 0.);
+
+            let touching = match rapier_context.cast_shape(
+                translation.truncate(),
+                0.,
+                direction,
+                &side,
+                half_extents.x,
+                QueryFilter::new()
+                    .exclude_sensors()
+                    .exclude_rigid_body(entity)
+                    .predicate(&is_solid),
+            ) {
+                Some((_, toi)) => toi.toi <= 0. || toi.normal1.dot(direction) >= min_normal_x,
+                None => false,
+            };
+
+            match wall_side {
+                WallSide::Left => wall_detection.on_left_wall = touching,
+                WallSide::Right => wall_detection.on_right_wall = touching,
+            }
         }
     }
 }
@@ -356,11 +664,47 @@ pub fn kill_out_of_bounds_player(
             .px_wid as f32;
 
         let translation = transform.translation;
-        if translation.x > right_bound || translation.x < left_bound || translation.y < bottom_bound
+        if (translation.x > right_bound
+            || translation.x < left_bound
+            || translation.y < bottom_bound)
+            && *vitality == Vitality::Alive
         {
-            if *vitality != Vitality::Dead {
-                *vitality = Vitality::Dead;
+            *vitality = Vitality::Dead(DeathCause::Fell);
+        }
+    }
+}
+
+/// Kills the player when they are squeezed between solids they can't be pushed out of.
+///
+/// This mostly happens when a platform moves the player into a wall or another platform.
+pub fn kill_crushed_player(
+    mut players: Query<(&CharacterController, &mut Vitality), With<Player>>,
+) {
+    for (controller, mut vitality) in players.iter_mut() {
+        if controller.crushed && *vitality == Vitality::Alive {
+            *vitality = Vitality::Dead(DeathCause::Crushed);
+        }
+    }
+}
+
+/// The entity that an alive player has just started touching, if the collision is one.
+///
+/// Touches while time goes backwards are ignored, since they're being undone.
+pub fn player_touched(
+    collision: &CollisionEvent,
+    player_query: &Query<&Vitality, With<Player>>,
+    time_scale: &TimeScale,
+) -> Option<Entity> {
+    match collision {
+        CollisionEvent::Started(a, b, _) if time_scale.0 > 0. => {
+            if matches!(player_query.get(*a), Ok(Vitality::Alive)) {
+                Some(*b)
+            } else if matches!(player_query.get(*b), Ok(Vitality::Alive)) {
+                Some(*a)
+            } else {
+                None
             }
         }
+        _ => None,
     }
 }
@@ -0,0 +1,67 @@
+//! Statistics about the current attempt at a level, which are sent out with [LevelComplete] when
+//! a goal is reached.
+use crate::{
+    player::{Player, Vitality},
+    GameState,
+};
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use std::time::Duration;
+
+pub struct RunStatsPlugin;
+
+impl Plugin for RunStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunStats>()
+            .add_event::<LevelComplete>()
+            .add_exit_system(GameState::Preamble, start_run_stats)
+            .add_system(count_deaths.run_in_state(GameState::Gameplay));
+    }
+}
+
+/// Counts of what happened since the player last entered a level from its preamble.
+///
+/// Restarting the level doesn't reset them.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct RunStats {
+    /// [Time::time_since_startup] when the level was entered.
+    pub started: Duration,
+    pub deaths: u32,
+    /// Times the level was restarted, or sent back to a checkpoint.
+    pub restarts: u32,
+    pub rewinds: u32,
+    pub fast_forwards: u32,
+}
+
+/// Event sent when the player reaches a goal, just before moving on to the next level.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LevelComplete {
+    pub level_index: usize,
+    pub level_iid: String,
+    /// [TimeSinceLevelStart](crate::history::TimeSinceLevelStart) when the goal was reached.
+    pub level_time: f32,
+    /// Time really spent on the level, including time spent paused, dead, or restarting.
+    pub real_time: Duration,
+    pub deaths: u32,
+    pub restarts: u32,
+    pub rewinds: u32,
+    pub fast_forwards: u32,
+}
+
+fn start_run_stats(mut run_stats: ResMut<RunStats>, time: Res<Time>) {
+    *run_stats = RunStats {
+        started: time.time_since_startup(),
+        ..default()
+    };
+}
+
+fn count_deaths(
+    player_query: Query<&Vitality, (With<Player>, Changed<Vitality>)>,
+    mut run_stats: ResMut<RunStats>,
+) {
+    for vitality in player_query.iter() {
+        if let Vitality::Dead(_) = vitality {
+            run_stats.deaths += 1;
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Switches and pressure plates, which activate the platforms they point at.
+//!
+//! Platforms linked to one only move along their path while it's activated.
+//!
+//! Switch flips are recorded in a [History], so rewinding undoes them.
+//! Pressure plates aren't recorded, since they only follow whatever is standing on them right now.
+use crate::{
+    fixed_timestep::PhysicsAppExt,
+    history::{record_touches, rewind_history, History, Touchable},
+    ldtk_fields::entity_refs,
+    player::{ColliderBundle, GroundDetection, Player},
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+
+pub struct SwitchPlugin;
+
+impl Plugin for SwitchPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<SwitchBundle>("Switch")
+            .register_ldtk_entity::<PressurePlateBundle>("PressurePlate")
+            .add_system(
+                record_touches::<Activated>
+                    .run_in_state(GameState::Gameplay)
+                    .after("update_time"),
+            )
+            .add_system(
+                rewind_history::<Activated>
+                    .run_in_state(GameState::Gameplay)
+                    .after("update_time"),
+            )
+            .add_physics_system(
+                press_plates
+                    .run_in_state(GameState::Gameplay)
+                    .after("ground_detection")
+                    .before("platform_movement"),
+            )
+            .add_system(show_activation.run_in_state(GameState::Gameplay));
+    }
+}
+
+const SWITCH_TILE: usize = 108;
+
+const PRESSURE_PLATE_TILE: usize = 110;
+
+/// Whether a switch or pressure plate is currently activating its [ActivationTargets].
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct Activated(pub bool);
+
+/// Touching a switch flips it.
+impl Touchable for Activated {
+    fn touched(self) -> Self {
+        Activated(!self.0)
+    }
+}
+
+/// Iids of the platforms a switch or pressure plate activates.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct ActivationTargets(pub Vec<String>);
+
+impl From<EntityInstance> for ActivationTargets {
+    fn from(entity_instance: EntityInstance) -> Self {
+        ActivationTargets(entity_refs(&entity_instance, "Targets"))
+    }
+}
+
+/// Stays activated after the player touches it, until they touch it again.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct Switch;
+
+/// Only activated while the player stands on it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct PressurePlate;
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct SwitchBundle {
+    #[sprite_sheet_bundle]
+    #[bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    pub switch: Switch,
+    pub activated: Activated,
+    #[from_entity_instance]
+    pub targets: ActivationTargets,
+    #[from_entity_instance]
+    #[bundle]
+    pub collider_bundle: ColliderBundle,
+    pub sensor: Sensor,
+    pub history: History<Activated>,
+}
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct PressurePlateBundle {
+    #[sprite_sheet_bundle]
+    #[bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    pub pressure_plate: PressurePlate,
+    pub activated: Activated,
+    #[from_entity_instance]
+    pub targets: ActivationTargets,
+    #[from_entity_instance]
+    #[bundle]
+    pub collider_bundle: ColliderBundle,
+}
+
+/// Whether anything activates the platform with the given iid, or [None] if nothing links to it.
+pub fn activation<'a>(
+    iid: &str,
+    activators: impl Iterator<Item = (&'a ActivationTargets, &'a Activated)>,
+) -> Option<bool> {
+    let mut activation = None;
+
+    for (targets, activated) in activators {
+        if targets.0.iter().any(|target| target == iid) {
+            activation = Some(activation.unwrap_or(false) || activated.0);
+        }
+    }
+
+    activation
+}
+
+fn press_plates(
+    player_query: Query<&GroundDetection, With<Player>>,
+    mut plate_query: Query<(Entity, &mut Activated), With<PressurePlate>>,
+) {
+    for (plate_entity, mut activated) in plate_query.iter_mut() {
+        let pressed = player_query.iter().any(|ground_detection| {
+            ground_detection.on_ground && ground_detection.ground_entity == Some(plate_entity)
+        });
+
+        if activated.0 != pressed {
+            activated.0 = pressed;
+        }
+    }
+}
+
+fn show_activation(
+    mut query: Query<(&Activated, &mut TextureAtlasSprite, Option<&Switch>), Changed<Activated>>,
+) {
+    for (activated, mut sprite, switch) in query.iter_mut() {
+        let tile = if switch.is_some() {
+            SWITCH_TILE
+        } else {
+            PRESSURE_PLATE_TILE
+        };
+
+        sprite.index = tile + activated.0 as usize;
+    }
+}
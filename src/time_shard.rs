@@ -0,0 +1,118 @@
+//! Optional collectibles hidden around levels.
+//!
+//! A collected shard vanishes, but shows up again if time is rewound past when it was taken.
+//! Shards only count towards a level's total once the level is completed.
+use crate::{
+    history::{record_touches, rewind_history, History, Touchable},
+    ldtk_fields::EntityIid,
+    player::ColliderBundle,
+    run_stats::LevelComplete,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_ecs_ldtk::prelude::*;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+pub struct TimeShardPlugin;
+
+impl Plugin for TimeShardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CollectedShards>()
+            .register_ldtk_entity::<TimeShardBundle>("TimeShard")
+            .add_system(
+                record_touches::<Collected>
+                    .run_in_state(GameState::Gameplay)
+                    .after("update_time"),
+            )
+            .add_system(
+                rewind_history::<Collected>
+                    .run_in_state(GameState::Gameplay)
+                    .after("update_time"),
+            )
+            .add_system(
+                record_collected_shards
+                    .run_on_event::<LevelComplete>()
+                    .after("victory"),
+            )
+            .add_system(show_shards.run_in_state(GameState::Gameplay));
+    }
+}
+
+/// Whether a [TimeShard] has been picked up in the current run of the level.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct Collected(pub bool);
+
+impl Touchable for Collected {
+    fn touched(self) -> Self {
+        Collected(true)
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub struct TimeShard;
+
+#[derive(Clone, Default, Bundle, LdtkEntity)]
+pub struct TimeShardBundle {
+    #[sprite_sheet_bundle]
+    #[bundle]
+    pub sprite_sheet_bundle: SpriteSheetBundle,
+    pub time_shard: TimeShard,
+    #[from_entity_instance]
+    pub iid: EntityIid,
+    pub collected: Collected,
+    pub history: History<Collected>,
+    #[from_entity_instance]
+    #[bundle]
+    pub collider_bundle: ColliderBundle,
+    pub sensor: Sensor,
+}
+
+/// Iids of the shards collected in levels that have been completed, by level iid.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct CollectedShards(pub HashMap<String, HashSet<String>>);
+
+impl CollectedShards {
+    pub fn count(&self, level_iid: &str) -> usize {
+        self.0.get(level_iid).map(HashSet::len).unwrap_or(0)
+    }
+}
+
+/// Number of shards in the level at `level_index`.
+pub fn level_shard_total(ldtk_asset: &LdtkAsset, level_index: usize) -> usize {
+    ldtk_asset
+        .iter_levels()
+        .nth(level_index)
+        .and_then(|level| level.layer_instances.as_ref())
+        .into_iter()
+        .flatten()
+        .flat_map(|layer| layer.entity_instances.iter())
+        .filter(|entity_instance| entity_instance.identifier == "TimeShard")
+        .count()
+}
+
+fn record_collected_shards(
+    mut level_complete_events: EventReader<LevelComplete>,
+    shard_query: Query<(&EntityIid, &Collected), With<TimeShard>>,
+    mut collected_shards: ResMut<CollectedShards>,
+) {
+    for level_complete in level_complete_events.iter() {
+        let level_shards = collected_shards
+            .0
+            .entry(level_complete.level_iid.clone())
+            .or_default();
+
+        for (iid, collected) in shard_query.iter() {
+            if collected.0 {
+                level_shards.insert(iid.0.clone());
+            }
+        }
+    }
+}
+
+fn show_shards(mut shard_query: Query<(&Collected, &mut Visibility), Changed<Collected>>) {
+    for (collected, mut visibility) in shard_query.iter_mut() {
+        visibility.is_visible = !collected.0;
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     history::TimeSinceLevelStart,
-    player::Vitality,
+    player::{DeathCause, Vitality},
     previous_component::{PreviousComponent, PreviousComponentPlugin, TrackPreviousComponent},
     AssetHolder, GameState,
 };
@@ -143,7 +143,14 @@ fn death_screen(
             commands.entity(entity).despawn_recursive();
         }
 
-        if *changed_vitality == Vitality::Dead {
+        if let Vitality::Dead(cause) = changed_vitality {
+            let cause_text = match cause {
+                DeathCause::Fell => "Malcolm fell into the abyss",
+                DeathCause::Impact => "Apophis hit the earth",
+                DeathCause::Hazard => "Malcolm touched something nasty",
+                DeathCause::Crushed => "Malcolm was crushed",
+            };
+
             commands
                 .spawn_bundle(NodeBundle {
                     style: Style {
@@ -163,7 +170,7 @@ fn death_screen(
                 .with_children(|builder| {
                     builder.spawn_bundle(TextBundle {
                         text: Text::from_section(
-                            "DEAD\n\nPress R to restart..",
+                            format!("DEAD\n\n{cause_text}\n\nPress R to restart.."),
                             TextStyle {
                                 font: asset_holder.font.clone(),
                                 font_size: 128.,
@@ -187,6 +194,7 @@ fn death_screen(
 pub enum UiAction {
     Debug(&'static str),
     SelectLevel(usize),
+    TogglePathPreview,
 }
 
 /// System that detects button presses and fires [UiAction]s.
@@ -1,4 +1,7 @@
-use crate::GameState;
+use crate::{
+    wall_merge::{GridRect, WallMerge},
+    GameState,
+};
 use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 use bevy_rapier2d::prelude::*;
@@ -11,6 +14,80 @@ pub struct Wall;
 #[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
 pub struct WallBundle {
     wall: Wall,
+    #[from_int_grid_cell]
+    material: SurfaceMaterial,
+}
+
+/// What a wall is made of, which changes how characters move along it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Component)]
+pub enum SurfaceMaterial {
+    #[default]
+    Stone,
+    /// Slippery, so characters are slow to speed up and slow down.
+    Ice,
+    /// Sticky, so characters wade through it slowly.
+    Mud,
+    /// Springy, so characters bounce off of it.
+    Bouncy,
+}
+
+impl From<IntGridCell> for SurfaceMaterial {
+    fn from(int_grid_cell: IntGridCell) -> SurfaceMaterial {
+        match int_grid_cell.value {
+            5 => SurfaceMaterial::Ice,
+            6 => SurfaceMaterial::Mud,
+            7 => SurfaceMaterial::Bouncy,
+            _ => SurfaceMaterial::Stone,
+        }
+    }
+}
+
+impl SurfaceMaterial {
+    pub fn friction(&self) -> Friction {
+        match self {
+            SurfaceMaterial::Ice => Friction::coefficient(0.05),
+            SurfaceMaterial::Mud => Friction::coefficient(1.),
+            _ => Friction::default(),
+        }
+    }
+
+    pub fn restitution(&self) -> Restitution {
+        match self {
+            SurfaceMaterial::Bouncy => Restitution::coefficient(0.85),
+            _ => Restitution::default(),
+        }
+    }
+}
+
+/// Walls that can be jumped up through, and only block things landing on them from above.
+///
+/// Characters can also drop down through them.
+/// See [CharacterController](crate::character_controller::CharacterController).
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Component)]
+pub struct OneWay;
+
+impl OneWay {
+    /// How far something's feet can sink below the top of a one-way wall and still be held up.
+    const TOLERANCE: f32 = 1.;
+
+    /// Whether a one-way wall with this transform and collider holds up feet at height `feet`.
+    pub fn supports(global_transform: &GlobalTransform, collider: &Collider, feet: f32) -> bool {
+        let (scale, _, translation) = global_transform.to_scale_rotation_translation();
+        let half_height = collider
+            .as_cuboid()
+            .map(|cuboid| cuboid.half_extents().y)
+            .unwrap_or_default()
+            * scale.y;
+
+        feet >= translation.y + half_height - OneWay::TOLERANCE
+    }
+}
+
+#[derive(Clone, Debug, Default, Bundle, LdtkIntCell)]
+pub struct OneWayBundle {
+    wall: Wall,
+    material: SurfaceMaterial,
+    one_way: OneWay,
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
@@ -18,20 +95,77 @@ pub struct WallPlugin;
 
 impl Plugin for WallPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(
-            spawn_wall_collision
-                .run_in_state(GameState::Gameplay)
-                .run_on_event::<LevelEvent>(),
-        )
-        .add_system(
-            spawn_wall_collision
-                .run_in_state(GameState::Preamble)
-                .run_on_event::<LevelEvent>(),
-        )
-        .register_ldtk_int_cell::<WallBundle>(1);
+        app.init_resource::<WallMerge>()
+            .init_resource::<WallCache>()
+            .add_system(forget_modified_levels.before("spawn_wall_collision"))
+            .add_system(
+                spawn_wall_collision
+                    .run_in_state(GameState::Gameplay)
+                    .label("spawn_wall_collision")
+                    .run_on_event::<LevelEvent>(),
+            )
+            .add_system(
+                spawn_wall_collision
+                    .run_in_state(GameState::Preamble)
+                    .label("spawn_wall_collision")
+                    .run_on_event::<LevelEvent>(),
+            )
+            .register_ldtk_int_cell::<WallBundle>(1)
+            .register_ldtk_int_cell::<OneWayBundle>(4)
+            .register_ldtk_int_cell::<WallBundle>(5)
+            .register_ldtk_int_cell::<WallBundle>(6)
+            .register_ldtk_int_cell::<WallBundle>(7);
+    }
+}
+
+/// Rectangles that the walls of one IntGrid value in a level were merged into.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+struct WallColliders {
+    material: SurfaceMaterial,
+    one_way: bool,
+    rects: Vec<GridRect>,
+}
+
+/// The wall colliders of every level that has been spawned before, by level iid.
+///
+/// Restarting or revisiting a level reuses them, instead of merging its walls all over again.
+#[derive(Clone, Debug, Default)]
+pub struct WallCache(HashMap<String, Vec<WallColliders>>);
+
+/// Forgets the walls of levels that have changed on disk, so they get merged again.
+fn forget_modified_levels(
+    mut level_events: EventReader<AssetEvent<LdtkLevel>>,
+    levels: Res<Assets<LdtkLevel>>,
+    wall_merge: Res<WallMerge>,
+    mut wall_cache: ResMut<WallCache>,
+) {
+    // The cached rectangles depend on how they were merged, too
+    if wall_merge.is_changed() {
+        wall_cache.0.clear();
+    }
+
+    for event in level_events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            if let Some(level) = levels.get(handle) {
+                wall_cache.0.remove(&level.level.iid);
+            }
+        }
     }
 }
 
+type NewWalls<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static GridCoords,
+        &'static Parent,
+        &'static IntGridCell,
+        &'static SurfaceMaterial,
+        Option<&'static OneWay>,
+    ),
+    Added<Wall>,
+>;
+
 /// Spawns rapier collisions for the walls of a level
 ///
 /// You could just insert a ColliderBundle in to the WallBundle,
@@ -40,38 +174,50 @@ impl Plugin for WallPlugin {
 ///
 /// Instead, by flagging the wall tiles and spawning the collisions later,
 /// we can minimize the amount of colliding entities.
-///
-/// The algorithm used here is a nice compromise between simplicity, speed,
-/// and a small number of rectangle colliders.
 /// In basic terms, it will:
 /// 1. consider where the walls are
-/// 2. combine wall tiles into flat "plates" in each individual row
-/// 3. combine the plates into rectangles across multiple rows wherever possible
-/// 4. spawn colliders for each rectangle
+/// 2. combine them into rectangles, using the level's [WallMerge]
+/// 3. spawn colliders for each rectangle
+///
+/// Each IntGrid value is merged separately, so that the colliders can carry the [SurfaceMaterial]
+/// of their tiles, and whether they're [OneWay].
+///
+/// The rectangles are kept in the [WallCache], so steps 1 and 2 only happen the first time a
+/// level is spawned.
 fn spawn_wall_collision(
     mut commands: Commands,
-    wall_query: Query<(&GridCoords, &Parent), Added<Wall>>,
+    wall_query: NewWalls,
     parent_query: Query<&Parent, Without<Wall>>,
     level_query: Query<(Entity, &Handle<LdtkLevel>)>,
     levels: Res<Assets<LdtkLevel>>,
+    wall_merge: Res<WallMerge>,
+    mut wall_cache: ResMut<WallCache>,
 ) {
-    /// Represents a wide wall that is 1 tile tall
-    /// Used to spawn wall collisions
-    #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
-    struct Plate {
-        left: i32,
-        right: i32,
+    /// Walls of the same IntGrid value in a level, which are merged together
+    #[derive(Clone, Eq, PartialEq, Debug, Default)]
+    struct WallGroup {
+        material: SurfaceMaterial,
+        one_way: bool,
+        locations: HashSet<GridCoords>,
     }
 
-    /// A simple rectangle type representing a wall of any size
-    #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
-    struct Rect {
-        left: i32,
-        right: i32,
-        top: i32,
-        bottom: i32,
+    if wall_query.is_empty() {
+        return;
     }
 
+    // Levels that have been spawned before already have their rectangles,
+    // so there's no need to look at where their walls are
+    let cached_levels: HashSet<Entity> = level_query
+        .iter()
+        .filter(|(_, level_handle)| {
+            levels
+                .get(*level_handle)
+                .map(|level| wall_cache.0.contains_key(&level.level.iid))
+                .unwrap_or(false)
+        })
+        .map(|(level_entity, _)| level_entity)
+        .collect();
+
     // Consider where the walls are
     // storing them as GridCoords in a HashSet for quick, easy lookup
     //
@@ -79,129 +225,112 @@ fn spawn_wall_collision(
     // This has two consequences in the resulting collision entities:
     // 1. it forces the walls to be split along level boundaries
     // 2. it lets us easily add the collision entities as children of the appropriate level entity
-    let mut level_to_wall_locations: HashMap<Entity, HashSet<GridCoords>> = HashMap::new();
-
-    wall_query.for_each(|(&grid_coords, parent)| {
-        // An intgrid tile's direct parent will be a layer entity, not the level entity
-        // To get the level entity, you need the tile's grandparent.
-        // This is where parent_query comes in.
-        if let Ok(grandparent) = parent_query.get(parent.get()) {
-            level_to_wall_locations
-                .entry(grandparent.get())
-                .or_insert(HashSet::new())
-                .insert(grid_coords);
-        }
-    });
-
-    if !wall_query.is_empty() {
-        level_query.for_each(|(level_entity, level_handle)| {
-            if let Some(level_walls) = level_to_wall_locations.get(&level_entity) {
-                let level = levels
-                    .get(level_handle)
-                    .expect("Level should be loaded by this point");
-
-                let LayerInstance {
-                    c_wid: width,
-                    c_hei: height,
-                    grid_size,
-                    ..
-                } = level
-                    .level
-                    .layer_instances
-                    .clone()
-                    .expect("Level asset should have layers")[0];
-
-                // combine wall tiles into flat "plates" in each individual row
-                let mut plate_stack: Vec<Vec<Plate>> = Vec::new();
-
-                for y in 0..height {
-                    let mut row_plates: Vec<Plate> = Vec::new();
-                    let mut plate_start = None;
-
-                    // + 1 to the width so the algorithm "terminates" plates that touch the right
-                    // edge
-                    for x in 0..width + 1 {
-                        match (plate_start, level_walls.contains(&GridCoords { x, y })) {
-                            (Some(s), false) => {
-                                row_plates.push(Plate {
-                                    left: s,
-                                    right: x - 1,
-                                });
-                                plate_start = None;
-                            }
-                            (None, true) => plate_start = Some(x),
-                            _ => (),
-                        }
-                    }
+    //
+    // Walls are also keyed by their IntGrid value, so different kinds of wall never merge.
+    let mut level_to_wall_locations: HashMap<(Entity, i32), WallGroup> = HashMap::new();
+    let mut levels_with_walls: HashSet<Entity> = HashSet::new();
 
-                    plate_stack.push(row_plates);
+    wall_query.for_each(
+        |(&grid_coords, parent, int_grid_cell, &material, one_way)| {
+            // An intgrid tile's direct parent will be a layer entity, not the level entity
+            // To get the level entity, you need the tile's grandparent.
+            // This is where parent_query comes in.
+            if let Ok(grandparent) = parent_query.get(parent.get()) {
+                let level_entity = grandparent.get();
+                levels_with_walls.insert(level_entity);
+
+                if !cached_levels.contains(&level_entity) {
+                    level_to_wall_locations
+                        .entry((level_entity, int_grid_cell.value))
+                        .or_insert(WallGroup {
+                            material,
+                            one_way: one_way.is_some(),
+                            locations: HashSet::new(),
+                        })
+                        .locations
+                        .insert(grid_coords);
                 }
+            }
+        },
+    );
 
-                // combine "plates" into rectangles across multiple rows
-                let mut wall_rects: Vec<Rect> = Vec::new();
-                let mut previous_rects: HashMap<Plate, Rect> = HashMap::new();
-
-                // an extra empty row so the algorithm "terminates" the rects that touch the top
-                // edge
-                plate_stack.push(Vec::new());
-
-                for (y, row) in plate_stack.iter().enumerate() {
-                    let mut current_rects: HashMap<Plate, Rect> = HashMap::new();
-                    for plate in row {
-                        if let Some(previous_rect) = previous_rects.remove(plate) {
-                            current_rects.insert(
-                                *plate,
-                                Rect {
-                                    top: previous_rect.top + 1,
-                                    ..previous_rect
-                                },
-                            );
-                        } else {
-                            current_rects.insert(
-                                *plate,
-                                Rect {
-                                    bottom: y as i32,
-                                    top: y as i32,
-                                    left: plate.left,
-                                    right: plate.right,
-                                },
-                            );
-                        }
-                    }
+    for level_entity in levels_with_walls {
+        if let Ok((_, level_handle)) = level_query.get(level_entity) {
+            let level = levels
+                .get(level_handle)
+                .expect("Level should be loaded by this point");
 
-                    // Any plates that weren't removed above have terminated
-                    wall_rects.append(&mut previous_rects.values().copied().collect());
-                    previous_rects = current_rects;
-                }
+            let LayerInstance {
+                c_wid: width,
+                c_hei: height,
+                grid_size,
+                ..
+            } = level
+                .level
+                .layer_instances
+                .clone()
+                .expect("Level asset should have layers")[0];
+
+            let greedy_walls = level
+                .level
+                .field_instances
+                .iter()
+                .any(|f| f.identifier == "GreedyWalls" && f.value == FieldValue::Bool(true));
+            let level_merge = if greedy_walls {
+                WallMerge::Greedy
+            } else {
+                *wall_merge
+            };
 
-                commands.entity(level_entity).with_children(|level| {
-                    // Spawn colliders for every rectangle..
-                    // Making the collider a child of the level serves two purposes:
-                    // 1. Adjusts the transforms to be relative to the level for free
-                    // 2. the colliders will be despawned automatically when levels unload
-                    for wall_rect in wall_rects {
-                        level
-                            .spawn()
-                            .insert(Collider::cuboid(
-                                (wall_rect.right as f32 - wall_rect.left as f32 + 1.)
-                                    * grid_size as f32
-                                    / 2.,
-                                (wall_rect.top as f32 - wall_rect.bottom as f32 + 1.)
-                                    * grid_size as f32
-                                    / 2.,
-                            ))
-                            .insert(RigidBody::Fixed)
-                            .insert(Transform::from_xyz(
-                                (wall_rect.left + wall_rect.right + 1) as f32 * grid_size as f32
-                                    / 2.,
-                                (wall_rect.bottom + wall_rect.top + 1) as f32 * grid_size as f32
-                                    / 2.,
-                                0.,
-                            ))
-                            .insert(GlobalTransform::default());
+            let level_colliders =
+                wall_cache
+                    .0
+                    .entry(level.level.iid.clone())
+                    .or_insert_with(|| {
+                        level_to_wall_locations
+                            .iter()
+                            .filter(|((wall_level, _), _)| *wall_level == level_entity)
+                            .map(|(_, wall_group)| WallColliders {
+                                material: wall_group.material,
+                                one_way: wall_group.one_way,
+                                rects: level_merge.merge(width, height, &wall_group.locations),
+                            })
+                            .collect()
+                    });
+
+            commands.entity(level_entity).with_children(|level| {
+                // Spawn colliders for every rectangle..
+                // Making the collider a child of the level serves two purposes:
+                // 1. Adjusts the transforms to be relative to the level for free
+                // 2. the colliders will be despawned automatically when levels unload
+                for wall_colliders in level_colliders.iter() {
+                    for wall_rect in wall_colliders.rects.iter() {
+                        let mut wall = level.spawn();
+
+                        wall.insert(Collider::cuboid(
+                            (wall_rect.right as f32 - wall_rect.left as f32 + 1.)
+                                * grid_size as f32
+                                / 2.,
+                            (wall_rect.top as f32 - wall_rect.bottom as f32 + 1.)
+                                * grid_size as f32
+                                / 2.,
+                        ))
+                        .insert(RigidBody::Fixed)
+                        .insert(Transform::from_xyz(
+                            (wall_rect.left + wall_rect.right + 1) as f32 * grid_size as f32 / 2.,
+                            (wall_rect.bottom + wall_rect.top + 1) as f32 * grid_size as f32 / 2.,
+                            0.,
+                        ))
+                        .insert(GlobalTransform::default())
+                        .insert(wall_colliders.material.friction())
+                        .insert(wall_colliders.material.restitution());
+
+                        if wall_colliders.one_way {
+                            wall.insert(OneWay);
+                        }
                     }
-                });
-            }
-        });
+                }
+            });
+        }
     }
 }
@@ -0,0 +1,289 @@
+//! Covering grids of wall cells with as few rectangles as possible, so that walls need fewer
+//! colliders.
+//!
+//! These are pure functions of the grid, so they can be tested and benchmarked on their own.
+use bevy_ecs_ldtk::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// A rectangle of grid cells, including the cells along its edges.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub struct GridRect {
+    pub left: i32,
+    pub right: i32,
+    pub top: i32,
+    pub bottom: i32,
+}
+
+/// How wall tiles are combined into as few rectangle colliders as possible.
+///
+/// Levels can use [WallMerge::Greedy] with their "GreedyWalls" field, and every other level uses
+/// the one in this resource.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub enum WallMerge {
+    /// See [merge_plates].
+    #[default]
+    Plates,
+    /// See [merge_greedy].
+    Greedy,
+}
+
+impl WallMerge {
+    /// Covers the `cells` of a `width` by `height` grid with rectangles.
+    pub fn merge(&self, width: i32, height: i32, cells: &HashSet<GridCoords>) -> Vec<GridRect> {
+        match self {
+            WallMerge::Plates => merge_plates(width, height, cells),
+            WallMerge::Greedy => merge_greedy(width, height, cells),
+        }
+    }
+}
+
+/// Covers the `cells` of a `width` by `height` grid with rectangles.
+///
+/// The algorithm used here is a nice compromise between simplicity, speed,
+/// and a small number of rectangles.
+/// In basic terms, it will:
+/// 1. combine cells into flat "plates" in each individual row
+/// 2. combine the plates into rectangles across multiple rows wherever possible
+///
+/// Plates are only combined when they line up exactly,
+/// so irregular shapes can end up split into more rectangles than they need.
+pub fn merge_plates(width: i32, height: i32, cells: &HashSet<GridCoords>) -> Vec<GridRect> {
+    /// Represents a wide rectangle that is 1 cell tall
+    #[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
+    struct Plate {
+        left: i32,
+        right: i32,
+    }
+
+    // combine cells into flat "plates" in each individual row
+    let mut plate_stack: Vec<Vec<Plate>> = Vec::new();
+
+    for y in 0..height {
+        let mut row_plates: Vec<Plate> = Vec::new();
+        let mut plate_start = None;
+
+        // + 1 to the width so the algorithm "terminates" plates that touch the right edge
+        for x in 0..width + 1 {
+            match (plate_start, cells.contains(&GridCoords { x, y })) {
+                (Some(s), false) => {
+                    row_plates.push(Plate {
+                        left: s,
+                        right: x - 1,
+                    });
+                    plate_start = None;
+                }
+                (None, true) => plate_start = Some(x),
+                _ => (),
+            }
+        }
+
+        plate_stack.push(row_plates);
+    }
+
+    // combine "plates" into rectangles across multiple rows
+    let mut rects: Vec<GridRect> = Vec::new();
+    let mut previous_rects: HashMap<Plate, GridRect> = HashMap::new();
+
+    // an extra empty row so the algorithm "terminates" the rects that touch the top edge
+    plate_stack.push(Vec::new());
+
+    for (y, row) in plate_stack.iter().enumerate() {
+        let mut current_rects: HashMap<Plate, GridRect> = HashMap::new();
+        for plate in row {
+            if let Some(previous_rect) = previous_rects.remove(plate) {
+                current_rects.insert(
+                    *plate,
+                    GridRect {
+                        top: previous_rect.top + 1,
+                        ..previous_rect
+                    },
+                );
+            } else {
+                current_rects.insert(
+                    *plate,
+                    GridRect {
+                        bottom: y as i32,
+                        top: y as i32,
+                        left: plate.left,
+                        right: plate.right,
+                    },
+                );
+            }
+        }
+
+        // Any plates that weren't removed above have terminated
+        rects.extend(previous_rects.values().copied());
+        previous_rects = current_rects;
+    }
+
+    rects
+}
+
+/// Covers the `cells` of a `width` by `height` grid with rectangles.
+///
+/// Starting from the bottom left, every cell that isn't covered yet starts a rectangle,
+/// which is stretched as far right as it can go and then as far up as it can go.
+/// Unlike [merge_plates], rectangles don't need to line up to cover rows together,
+/// which gives fewer of them on irregular shapes.
+pub fn merge_greedy(width: i32, height: i32, cells: &HashSet<GridCoords>) -> Vec<GridRect> {
+    let index = |x: i32, y: i32| (y * width + x) as usize;
+
+    let mut uncovered: Vec<bool> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| cells.contains(&GridCoords { x, y })))
+        .collect();
+
+    let mut rects: Vec<GridRect> = Vec::new();
+
+    for bottom in 0..height {
+        for left in 0..width {
+            if !uncovered[index(left, bottom)] {
+                continue;
+            }
+
+            let mut right = left;
+            while right + 1 < width && uncovered[index(right + 1, bottom)] {
+                right += 1;
+            }
+
+            let mut top = bottom;
+            while top + 1 < height && (left..=right).all(|x| uncovered[index(x, top + 1)]) {
+                top += 1;
+            }
+
+            for y in bottom..=top {
+                for x in left..=right {
+                    uncovered[index(x, y)] = false;
+                }
+            }
+
+            rects.push(GridRect {
+                left,
+                right,
+                top,
+                bottom,
+            });
+        }
+    }
+
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(coords: &[(i32, i32)]) -> HashSet<GridCoords> {
+        coords.iter().map(|&(x, y)| GridCoords { x, y }).collect()
+    }
+
+    fn area(rects: &[GridRect]) -> i32 {
+        rects
+            .iter()
+            .map(|rect| (rect.right - rect.left + 1) * (rect.top - rect.bottom + 1))
+            .sum()
+    }
+
+    /// Asserts that `rects` cover exactly `cells`, without overlapping.
+    fn assert_covers(rects: &[GridRect], cells: &HashSet<GridCoords>) {
+        let mut covered = HashSet::new();
+
+        for rect in rects {
+            for x in rect.left..=rect.right {
+                for y in rect.bottom..=rect.top {
+                    assert!(
+                        covered.insert(GridCoords { x, y }),
+                        "{x}, {y} is covered twice"
+                    );
+                }
+            }
+        }
+
+        assert_eq!(&covered, cells);
+    }
+
+    const MERGES: [WallMerge; 2] = [WallMerge::Plates, WallMerge::Greedy];
+
+    #[test]
+    fn single_tile() {
+        let cells = cells(&[(2, 1)]);
+
+        for wall_merge in MERGES {
+            let rects = wall_merge.merge(4, 4, &cells);
+
+            assert_eq!(
+                rects,
+                vec![GridRect {
+                    left: 2,
+                    right: 2,
+                    top: 1,
+                    bottom: 1,
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn l_shape() {
+        // A column with a foot sticking out to the right at the bottom
+        let cells = cells(&[(0, 0), (1, 0), (2, 0), (0, 1), (0, 2), (0, 3)]);
+
+        for wall_merge in MERGES {
+            let rects = wall_merge.merge(3, 4, &cells);
+
+            assert_covers(&rects, &cells);
+            assert_eq!(rects.len(), 2);
+        }
+    }
+
+    #[test]
+    fn checkerboard() {
+        let coords: Vec<(i32, i32)> = (0..6)
+            .flat_map(|y| (0..6).map(move |x| (x, y)))
+            .filter(|(x, y)| (x + y) % 2 == 0)
+            .collect();
+        let cells = cells(&coords);
+
+        // No two cells touch, so none of them can be merged
+        for wall_merge in MERGES {
+            let rects = wall_merge.merge(6, 6, &cells);
+
+            assert_covers(&rects, &cells);
+            assert_eq!(rects.len(), cells.len());
+        }
+    }
+
+    #[test]
+    fn greedy_merges_staggered_rows() {
+        // A bump on the middle row stops it lining up with the rows above and below
+        let cells = cells(&[(0, 0), (1, 0), (0, 1), (1, 1), (2, 1), (0, 2), (1, 2)]);
+
+        let plates = merge_plates(3, 3, &cells);
+        let greedy = merge_greedy(3, 3, &cells);
+
+        assert_covers(&plates, &cells);
+        assert_covers(&greedy, &cells);
+        assert!(greedy.len() < plates.len());
+    }
+
+    #[test]
+    fn strategies_cover_equal_area() {
+        // A pseudo-random cave, the same every run
+        let mut seed: u32 = 51;
+        let coords: Vec<(i32, i32)> = (0..40)
+            .flat_map(|y| (0..40).map(move |x| (x, y)))
+            .filter(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (seed >> 16) % 100 < 55
+            })
+            .collect();
+        let cells = cells(&coords);
+
+        let plates = merge_plates(40, 40, &cells);
+        let greedy = merge_greedy(40, 40, &cells);
+
+        assert_covers(&plates, &cells);
+        assert_covers(&greedy, &cells);
+        assert_eq!(area(&plates), area(&greedy));
+        assert_eq!(area(&plates), cells.len() as i32);
+    }
+}